@@ -247,7 +247,7 @@ export const MainLand: React.FC<{}> = () => {
 
       // set image onchain
       const pixel = coordinateToIndex(x - WORLD_SIZE / 2, y - WORLD_SIZE / 2)
-      await service.setPixelImage(pixel, cid, width, height)
+      await service.setPixelImage(pixel, cid, width, height, file)
     }
   }
 
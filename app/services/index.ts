@@ -28,7 +28,7 @@ export interface ContractDataService {
   // write
   mintPixels(pixel: number, width: number, height: number): void
   pickPixels(pixel: number, width: number, height: number): void
-  setPixelImage(pixel: number, cid: string, width: number, height: number): void
+  setPixelImage(pixel: number, cid: string, width: number, height: number, file: File): void
 }
 
 const serviceSingleton: {[platform: number]: Promise<ContractDataService>} = {}
@@ -6,6 +6,7 @@ import { LotteryInfo, PixelImage } from '../types'
 const keyStore = new keyStores.BrowserLocalStorageKeyStore()
 const contractId = 'pixelland.dang1412.testnet'
 const nodeUrl = 'https://rpc.testnet.near.org'
+const IMAGE_PAGE_SIZE = 100
 
 const config: ConnectConfig = {
   networkId: 'testnet',
@@ -54,12 +55,44 @@ export class NearDataService implements ContractDataService {
   subscribeBalance(account: string, cb: (balance: string) => void): Promise<any> {
     throw new Error('Method not implemented.')
   }
-  setPixelImage(pixel: number, cid: string, width: number, height: number): void {
-    throw new Error('Method not implemented.')
+  async setPixelImage(pixel: number, cid: string, width: number, height: number, file: File) {
+    const acc = this.wallet.account()
+    // the contract stores the sha256 of the image bytes alongside the cid, hash the uploaded
+    // file rather than what a gateway serves for the cid
+    const digest = await crypto.subtle.digest('SHA-256', await file.arrayBuffer())
+    await acc.functionCall({
+      contractId,
+      args: {
+        root_id: `${pixel}`,
+        cid,
+        mime: file.type || 'image/png',
+        hash: Buffer.from(digest).toString('base64'),
+      },
+      methodName: 'set_block_image',
+      // storage deposit, the excess is refunded
+      attachedDeposit: utils.format.parseNearAmount('0.01') as any
+    })
   }
 
-  getPixelImages(): Promise<PixelImage[]> {
-    throw new Error('Method not implemented.')
+  async getPixelImages(): Promise<PixelImage[]> {
+    // get_block_images is paginated, a page shorter than the limit is the last one
+    const images: PixelImage[] = []
+    for (let fromIndex = 0; ; fromIndex += IMAGE_PAGE_SIZE) {
+      const args = { from_index: `${fromIndex}`, limit: IMAGE_PAGE_SIZE }
+      const rawResult = await provider.query({
+        request_type: 'call_function',
+        account_id: contractId,
+        method_name: 'get_block_images',
+        args_base64: Buffer.from(JSON.stringify(args)).toString('base64'),
+        finality: 'optimistic',
+      });
+
+      const res: any[] = JSON.parse(Buffer.from((rawResult as any).result).toString());
+      images.push(...res.map(img => ({ pixelId: Number(img.pixelId), cid: img.cid, w: img.w, h: img.h })))
+      if (res.length < IMAGE_PAGE_SIZE) {
+        return images
+      }
+    }
   }
 
   async getBalance(account: string): Promise<string> {
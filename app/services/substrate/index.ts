@@ -316,7 +316,7 @@ export class SubstrateDataService implements ContractDataService {
     }
   }
 
-  async setPixelImage(pixel: number, cid: string, width: number, height: number) {
+  async setPixelImage(pixel: number, cid: string, width: number, height: number, file: File) {
     if (this.currentAccount) {
       const tx = this._api.tx.pixelModule.setImage(pixel, cid, [width, height])
       await this.signTx(tx)
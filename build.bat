@@ -1,2 +1,6 @@
 cargo build --target wasm32-unknown-unknown --release
-copy target\wasm32-unknown-unknown\release\*.wasm res
\ No newline at end of file
+copy target\wasm32-unknown-unknown\release\*.wasm res
+cd vault-token
+cargo build --target wasm32-unknown-unknown --release
+cd ..
+copy vault-token\target\wasm32-unknown-unknown\release\*.wasm res
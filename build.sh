@@ -4,3 +4,6 @@ set -e
 RUSTFLAGS='-C link-arg=-s' cargo build --target wasm32-unknown-unknown --release
 cp target/wasm32-unknown-unknown/release/*.wasm ./res/
 
+# share token deployed for every vault, see `set_vault_token_code`
+(cd vault-token && RUSTFLAGS='-C link-arg=-s' cargo build --target wasm32-unknown-unknown --release)
+cp vault-token/target/wasm32-unknown-unknown/release/*.wasm ./res/
@@ -0,0 +1,172 @@
+use near_contract_standards::non_fungible_token::{refund_deposit, TokenId};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Promise};
+
+use crate::events::{AuctionBid, AuctionCancel, AuctionCreate, AuctionSettle};
+use crate::market::FEE_DENOMINATOR;
+use crate::*;
+
+/// A bid placed in the last 10 minutes pushes the end of the auction to 10 minutes after the bid.
+pub const AUCTION_EXTENSION: u64 = 10 * 60 * 1_000_000_000;
+/// Auctions can run for at most 30 days.
+pub const MAX_AUCTION_DURATION: u64 = 30 * 24 * 60 * 60 * 1_000_000_000;
+/// Every bid must top the previous one by at least 5%, in basis points.
+pub const MIN_BID_INCREMENT: u128 = 500;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Bid {
+    pub bidder_id: AccountId,
+    pub amount: U128,
+}
+
+/// English auction of a whole block, the highest bid is held by the contract until settlement.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Auction {
+    pub seller_id: AccountId,
+    pub reserve_price: U128,
+    pub end_at: U64,
+    pub highest_bid: Option<Bid>,
+}
+
+impl Auction {
+    /// Smallest amount the next bid must reach.
+    pub fn min_bid(&self) -> u128 {
+        match &self.highest_bid {
+            Some(bid) => bid.amount.0 + (bid.amount.0 * MIN_BID_INCREMENT / FEE_DENOMINATOR).max(1),
+            None => self.reserve_price.0,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionView {
+    pub root_id: TokenId,
+    pub auction: Auction,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Auction the block rooted at `root_id` until `end_at` (nanoseconds), bids below
+    /// `reserve_price` are rejected. A fixed-price listing of the block is cancelled.
+    ///
+    /// The attached deposit must cover the storage used, the excess is refunded.
+    #[payable]
+    pub fn create_auction(&mut self, root_id: TokenId, reserve_price: U128, end_at: U64) {
+        let initial_storage_usage = env::storage_usage();
+        let seller_id = self.internal_block_owner(&root_id);
+        assert_eq!(seller_id, env::predecessor_account_id(), "No permission to auction");
+        self.assert_not_on_auction(&root_id);
+        self.assert_not_staked(&root_id);
+        assert!(reserve_price.0 > 0, "Reserve price must be positive");
+        let now = env::block_timestamp();
+        assert!(end_at.0 > now, "Auction end must be in the future");
+        assert!(end_at.0 - now <= MAX_AUCTION_DURATION, "Auction is too long");
+
+        self.internal_remove_listing(&root_id);
+        self.auctions.insert(&root_id, &Auction { seller_id: seller_id.clone(), reserve_price, end_at, highest_bid: None });
+        AuctionCreate { seller_id: &seller_id, root_id: &root_id, reserve_price, end_at }.emit();
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage));
+    }
+
+    /// Bid the attached deposit on the block rooted at `root_id`, the outbid bidder is refunded.
+    #[payable]
+    pub fn place_bid(&mut self, root_id: TokenId) {
+        let mut auction = self.auctions.get(&root_id).unwrap_or_else(|| env::panic_str("Block is not on auction"));
+        let bidder_id = env::predecessor_account_id();
+        let amount = env::attached_deposit();
+        let now = env::block_timestamp();
+        assert!(now < auction.end_at.0, "Auction has ended");
+        assert_ne!(bidder_id, auction.seller_id, "Seller cannot bid");
+        assert!(amount >= auction.min_bid(), "Bid is too low");
+
+        if let Some(bid) = auction.highest_bid.take() {
+            Promise::new(bid.bidder_id).transfer(bid.amount.0);
+        }
+        auction.highest_bid = Some(Bid { bidder_id: bidder_id.clone(), amount: U128(amount) });
+        if auction.end_at.0 - now < AUCTION_EXTENSION {
+            auction.end_at = U64(now + AUCTION_EXTENSION);
+        }
+        self.auctions.insert(&root_id, &auction);
+
+        AuctionBid { bidder_id: &bidder_id, root_id: &root_id, amount: U128(amount), end_at: auction.end_at }.emit();
+    }
+
+    /// Close an ended auction, callable by anyone. The block goes to the highest bidder and the
+    /// seller is paid the bid minus the market fee and royalties; without bids the block stays with the seller.
+    pub fn settle_auction(&mut self, root_id: TokenId) {
+        let auction = self.auctions.get(&root_id).unwrap_or_else(|| env::panic_str("Block is not on auction"));
+        assert!(env::block_timestamp() >= auction.end_at.0, "Auction has not ended");
+        self.auctions.remove(&root_id);
+
+        match &auction.highest_bid {
+            Some(bid) => {
+                self.internal_transfer_block(&root_id, &auction.seller_id, &bid.bidder_id, "auction");
+                self.internal_pay_sale(&root_id, &auction.seller_id, bid.amount.0);
+                AuctionSettle {
+                    seller_id: &auction.seller_id,
+                    winner_id: Some(&bid.bidder_id),
+                    root_id: &root_id,
+                    price: Some(bid.amount),
+                }
+                .emit();
+            }
+            None => {
+                AuctionSettle { seller_id: &auction.seller_id, winner_id: None, root_id: &root_id, price: None }.emit();
+            }
+        }
+    }
+
+    /// Cancel an auction that has no bids yet.
+    #[payable]
+    pub fn cancel_auction(&mut self, root_id: TokenId) {
+        assert_one_yocto();
+        let auction = self.auctions.get(&root_id).unwrap_or_else(|| env::panic_str("Block is not on auction"));
+        assert_eq!(auction.seller_id, env::predecessor_account_id(), "No permission to cancel auction");
+        assert!(auction.highest_bid.is_none(), "Auction has bids");
+
+        self.auctions.remove(&root_id);
+        AuctionCancel { root_id: &root_id }.emit();
+    }
+
+    pub fn get_auction(&self, root_id: TokenId) -> Option<Auction> {
+        self.auctions.get(&root_id)
+    }
+
+    pub fn get_auctions(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<AuctionView> {
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+        self.auctions
+            .iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .map(|(root_id, auction)| AuctionView { root_id, auction })
+            .collect()
+    }
+}
+
+impl Contract {
+    /// Blocks on auction cannot change hands nor shape outside of the auction.
+    pub(crate) fn assert_not_on_auction(&self, root_id: &TokenId) {
+        assert!(self.auctions.get(root_id).is_none(), "Block is on auction");
+    }
+
+    /// Close the auction of the block rooted at `root_id`, if any, without a sale. The highest
+    /// bidder is refunded.
+    pub(crate) fn internal_cancel_auction(&mut self, root_id: &TokenId) {
+        let auction = match self.auctions.remove(root_id) {
+            Some(auction) => auction,
+            None => return,
+        };
+        if let Some(bid) = auction.highest_bid {
+            Promise::new(bid.bidder_id).transfer(bid.amount.0);
+        }
+        AuctionCancel { root_id }.emit();
+    }
+}
@@ -0,0 +1,224 @@
+use near_contract_standards::non_fungible_token::events::{NftBurn, NftMint, NftTransfer};
+use near_contract_standards::non_fungible_token::metadata::TokenMetadata;
+use near_contract_standards::non_fungible_token::{refund_deposit, Token, TokenId};
+use near_sdk::serde_json::json;
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Balance, Promise};
+
+use crate::events::{BlockLock, BlockUnlock};
+use crate::*;
+
+/// Token ids of block tokens are the root cell id with this prefix, e.g. `block:7`.
+pub const BLOCK_TOKEN_PREFIX: &str = "block:";
+
+pub fn block_token_id(root_id: &str) -> TokenId {
+    format!("{}{}", BLOCK_TOKEN_PREFIX, root_id)
+}
+
+pub fn is_block_token(token_id: &str) -> bool {
+    token_id.starts_with(BLOCK_TOKEN_PREFIX)
+}
+
+/// Root cell id of a block token id.
+pub fn block_root_id(token_id: &str) -> Option<TokenId> {
+    token_id.strip_prefix(BLOCK_TOKEN_PREFIX).map(|root_id| root_id.to_string())
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Lock every cell of the merged block rooted at `root_id` in the contract and mint a single
+    /// `block:<root_id>` token to the caller, so the block can be traded as one NFT.
+    ///
+    /// The attached deposit must cover the storage of the block token, the excess is refunded.
+    #[payable]
+    pub fn nft_lock_block(&mut self, root_id: TokenId) -> Token {
+        let initial_storage_usage = env::storage_usage();
+        let owner_id = env::predecessor_account_id();
+        let rects = self.token_merge.get(&root_id).unwrap_or_else(|| env::panic_str("Token is not merged"));
+
+        let contract_id = env::current_account_id();
+        let cells = self.get_block_cells(root_id.clone());
+        for cell in &cells {
+            let cell_owner_id = expect_token_found(self.tokens.owner_by_id.get(cell));
+            assert_eq!(cell_owner_id, owner_id, "No permission to lock block");
+            if let Some(approvals_by_id) = &mut self.tokens.approvals_by_id {
+                approvals_by_id.remove(cell);
+            }
+            self.tokens.internal_transfer_unguarded(cell, &owner_id, &contract_id);
+        }
+
+        let block_id = block_token_id(&root_id);
+        let token = self.tokens.internal_mint_with_refund(
+            block_id.clone(),
+            owner_id.clone(),
+            Some(block_token_metadata(&root_id, &rects)),
+            None,
+        );
+
+        let cell_ids: Vec<&str> = cells.iter().map(|id| id.as_str()).collect();
+        NftTransfer {
+            old_owner_id: &owner_id,
+            new_owner_id: &contract_id,
+            token_ids: &cell_ids,
+            authorized_id: None,
+            memo: Some("lock block"),
+        }
+        .emit();
+        NftMint { owner_id: &owner_id, token_ids: &[&block_id], memo: None }.emit();
+        BlockLock { owner_id: &owner_id, root_id: &root_id, block_token_id: &block_id }.emit();
+
+        refund_deposit(env::storage_usage() - initial_storage_usage);
+
+        token
+    }
+
+    /// Burn the `block:<root_id>` token owned by the caller and give the locked cells back to them.
+    /// The cells stay merged, the released storage is refunded.
+    #[payable]
+    pub fn nft_unlock_block(&mut self, root_id: TokenId) {
+        assert_one_yocto();
+        let owner_id = env::predecessor_account_id();
+        let initial_storage_usage = env::storage_usage();
+
+        self.internal_unlock_block(&root_id, &owner_id);
+
+        let storage_released = initial_storage_usage - env::storage_usage();
+        Promise::new(owner_id).transfer(Balance::from(storage_released) * env::storage_byte_cost());
+    }
+
+    pub fn is_locked_block(&self, root_id: TokenId) -> bool {
+        self.tokens.owner_by_id.get(&block_token_id(&root_id)).is_some()
+    }
+}
+
+impl Contract {
+    /// Owner of the block rooted at `root_id`, the block token owner while the block is locked.
+    pub(crate) fn internal_block_owner(&self, root_id: &TokenId) -> AccountId {
+        assert!(is_cell_id(root_id), "Invalid cell id");
+        assert!(!self.is_covered_token(root_id), "Token is not a block root");
+        match self.tokens.owner_by_id.get(&block_token_id(root_id)) {
+            Some(owner_id) => owner_id,
+            None => expect_token_found(self.tokens.owner_by_id.get(root_id)),
+        }
+    }
+
+    /// Move the whole block rooted at `root_id` from `from` to `to`: the block token while the
+    /// block is locked, every cell otherwise. Approvals of the moved tokens are cleared.
+    pub(crate) fn internal_transfer_block(&mut self, root_id: &TokenId, from: &AccountId, to: &AccountId, memo: &str) {
+        let token_ids = if self.is_locked_block(root_id.clone()) {
+            vec![block_token_id(root_id)]
+        } else {
+            self.get_block_cells(root_id.clone())
+        };
+        for token_id in &token_ids {
+            let owner_id = expect_token_found(self.tokens.owner_by_id.get(token_id));
+            assert_eq!(&owner_id, from, "Block owner changed");
+            if let Some(approvals_by_id) = &mut self.tokens.approvals_by_id {
+                approvals_by_id.remove(token_id);
+            }
+            self.tokens.internal_transfer_unguarded(token_id, from, to);
+        }
+
+        let ids: Vec<&str> = token_ids.iter().map(|id| id.as_str()).collect();
+        NftTransfer { old_owner_id: from, new_owner_id: to, token_ids: &ids, authorized_id: None, memo: Some(memo) }.emit();
+        self.internal_on_transfer(&token_ids[0]);
+    }
+
+    /// Owner of a single cell, the block token owner while the cell is locked in a block.
+    pub(crate) fn internal_cell_owner(&self, token_id: &TokenId) -> AccountId {
+        let owner_id = expect_token_found(self.tokens.owner_by_id.get(token_id));
+        if owner_id != env::current_account_id() {
+            return owner_id;
+        }
+        let root_id = self.token_merged.get(token_id).unwrap_or_else(|| token_id.clone());
+        self.tokens.owner_by_id.get(&block_token_id(&root_id)).unwrap_or(owner_id)
+    }
+
+    pub(crate) fn internal_unlock_block(&mut self, root_id: &TokenId, owner_id: &AccountId) {
+        let block_id = block_token_id(root_id);
+        let block_owner_id = expect_token_found(self.tokens.owner_by_id.get(&block_id));
+        assert_eq!(&block_owner_id, owner_id, "No permission to unlock block");
+
+        self.internal_burn(&block_id, owner_id);
+
+        let contract_id = env::current_account_id();
+        let cells = self.get_block_cells(root_id.clone());
+        for cell in &cells {
+            self.tokens.internal_transfer_unguarded(cell, &contract_id, owner_id);
+        }
+
+        let cell_ids: Vec<&str> = cells.iter().map(|id| id.as_str()).collect();
+        NftBurn { owner_id, token_ids: &[&block_id], authorized_id: None, memo: None }.emit();
+        NftTransfer {
+            old_owner_id: &contract_id,
+            new_owner_id: owner_id,
+            token_ids: &cell_ids,
+            authorized_id: None,
+            memo: Some("unlock block"),
+        }
+        .emit();
+        BlockUnlock { owner_id, root_id, block_token_id: &block_id }.emit();
+    }
+
+    /// Remove `token_id` and everything the token standard stores for it.
+    fn internal_burn(&mut self, token_id: &TokenId, owner_id: &AccountId) {
+        self.tokens.owner_by_id.remove(token_id);
+        if let Some(token_metadata_by_id) = &mut self.tokens.token_metadata_by_id {
+            token_metadata_by_id.remove(token_id);
+        }
+        if let Some(tokens_per_owner) = &mut self.tokens.tokens_per_owner {
+            if let Some(mut owner_tokens) = tokens_per_owner.get(owner_id) {
+                owner_tokens.remove(token_id);
+                if owner_tokens.is_empty() {
+                    tokens_per_owner.remove(owner_id);
+                } else {
+                    tokens_per_owner.insert(owner_id, &owner_tokens);
+                }
+            }
+        }
+        if let Some(approvals_by_id) = &mut self.tokens.approvals_by_id {
+            approvals_by_id.remove(token_id);
+        }
+        if let Some(next_approval_id_by_id) = &mut self.tokens.next_approval_id_by_id {
+            next_approval_id_by_id.remove(token_id);
+        }
+    }
+}
+
+/// Metadata of a block token, `extra` holds the root, bounding box size and shape of the block.
+fn block_token_metadata(root_id: &str, rects: &[MergeRect]) -> TokenMetadata {
+    let (width, height) = shape_size(rects);
+    TokenMetadata {
+        title: Some(format!("Block {} ({}x{})", root_id, width, height)),
+        description: None,
+        media: None,
+        media_hash: None,
+        copies: Some(1u64),
+        issued_at: None,
+        expires_at: None,
+        starts_at: None,
+        updated_at: None,
+        extra: Some(json!({ "root": root_id, "width": width, "height": height, "rects": rects }).to_string()),
+        reference: None,
+        reference_hash: None,
+    }
+}
+
+/// Width and height of the bounding box of `rects`.
+pub(crate) fn shape_size(rects: &[MergeRect]) -> (u64, u64) {
+    let (_, width, height) = shape_bounds(rects);
+    (width, height)
+}
+
+/// Top-left cell, width and height of the bounding box of `rects`. The top-left cell is not
+/// part of the shape when the shape is not a rectangle.
+pub(crate) fn shape_bounds(rects: &[MergeRect]) -> (TokenId, u64, u64) {
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (i128::MAX, i128::MAX, i128::MIN, i128::MIN);
+    for rect in rects {
+        let (x, y) = get_coord(rect.token_id.clone());
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x + rect.width as i128);
+        max_y = max_y.max(y + rect.height as i128);
+    }
+    (get_token_id(min_x, min_y), (max_x - min_x) as u64, (max_y - min_y) as u64)
+}
@@ -0,0 +1,150 @@
+use near_contract_standards::non_fungible_token::events::NftMint;
+use near_contract_standards::non_fungible_token::metadata::TokenMetadata;
+use near_contract_standards::non_fungible_token::{Token, TokenId};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, Promise};
+
+use crate::events::{DutchAuctionCancel, DutchAuctionCreate, DutchAuctionSale};
+use crate::*;
+
+/// Primary sale of an unminted `width` x `height` zone whose price falls linearly from
+/// `start_price` at `start_at` to `floor_price` at `end_at`, then stays at the floor.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DutchAuction {
+    pub width: u8,
+    pub height: u8,
+    pub start_price: U128,
+    pub floor_price: U128,
+    pub start_at: U64,
+    pub end_at: U64,
+}
+
+impl DutchAuction {
+    /// Price of the whole zone at `timestamp`.
+    pub fn price_at(&self, timestamp: u64) -> u128 {
+        let (start_price, floor_price) = (self.start_price.0, self.floor_price.0);
+        if timestamp <= self.start_at.0 {
+            return start_price;
+        }
+        if timestamp >= self.end_at.0 {
+            return floor_price;
+        }
+        let elapsed = (timestamp - self.start_at.0) as u128;
+        let duration = (self.end_at.0 - self.start_at.0) as u128;
+        start_price - (start_price - floor_price) * elapsed / duration
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DutchAuctionView {
+    /// Top-left cell of the zone.
+    pub zone_id: TokenId,
+    pub auction: DutchAuction,
+    pub current_price: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Owner only, reserve the unminted zone of `auction.width` x `auction.height` cells from
+    /// `zone_id` and sell it by Dutch auction. Reserved cells cannot be minted at the flat mint price.
+    pub fn create_dutch_auction(&mut self, zone_id: TokenId, auction: DutchAuction) {
+        assert_eq!(env::predecessor_account_id(), self.tokens.owner_id, "Unauthorized");
+        assert!(auction.width > 0 && auction.height > 0, "Invalid zone size");
+        assert!(auction.start_price.0 >= auction.floor_price.0, "Start price is below floor price");
+        assert!(auction.end_at.0 > auction.start_at.0, "Auction end must be after start");
+        assert!(is_cell_id(&zone_id), "Invalid cell id");
+
+        iterate_token_area(zone_id.clone(), auction.width, auction.height, |cell| -> bool {
+            assert!(self.tokens.owner_by_id.get(&cell).is_none(), "Cell already minted");
+            assert!(self.dutch_auction_cells.get(&cell).is_none(), "Cell is reserved for a zone auction");
+            self.dutch_auction_cells.insert(&cell, &zone_id);
+            true
+        });
+
+        self.dutch_auctions.insert(&zone_id, &auction);
+        DutchAuctionCreate { zone_id: &zone_id, auction: &auction }.emit();
+    }
+
+    /// Buy the whole zone at its current price, minting every cell to the caller. Wish-list offers
+    /// on the cells are not executed. The excess of the attached deposit is refunded.
+    #[payable]
+    pub fn buy_dutch_auction(&mut self, zone_id: TokenId, token_metadata: TokenMetadata) -> Vec<Token> {
+        let auction = self.dutch_auctions.get(&zone_id).unwrap_or_else(|| env::panic_str("Zone is not on auction"));
+        let now = env::block_timestamp();
+        assert!(now >= auction.start_at.0, "Zone auction has not started");
+        let price = auction.price_at(now);
+        let deposit = env::attached_deposit();
+        assert!(deposit >= price, "Not enough attached to buy zone");
+
+        let buyer_id = env::predecessor_account_id();
+        self.internal_remove_dutch_auction(&zone_id, &auction);
+
+        let mut tokens: Vec<Token> = Vec::new();
+        iterate_token_area(zone_id.clone(), auction.width, auction.height, |cell| -> bool {
+            let token = self.tokens.internal_mint_with_refund(cell, buyer_id.clone(), Some(token_metadata.clone()), None);
+            tokens.push(token);
+            true
+        });
+
+        let token_ids: Vec<&str> = tokens.iter().map(|token| token.token_id.as_str()).collect();
+        NftMint { owner_id: &buyer_id, token_ids: &token_ids, memo: Some("dutch auction") }.emit();
+        DutchAuctionSale { buyer_id: &buyer_id, zone_id: &zone_id, price: U128(price) }.emit();
+        for token in &tokens {
+            self.minters.insert(&token.token_id, &token.owner_id);
+        }
+
+        if deposit > price {
+            Promise::new(buyer_id).transfer(deposit - price);
+        }
+
+        tokens
+    }
+
+    /// Owner only, stop an unsold zone auction and release its cells for regular minting.
+    pub fn cancel_dutch_auction(&mut self, zone_id: TokenId) {
+        assert_eq!(env::predecessor_account_id(), self.tokens.owner_id, "Unauthorized");
+        let auction = self.dutch_auctions.get(&zone_id).unwrap_or_else(|| env::panic_str("Zone is not on auction"));
+        self.internal_remove_dutch_auction(&zone_id, &auction);
+        DutchAuctionCancel { zone_id: &zone_id }.emit();
+    }
+
+    pub fn get_dutch_auction(&self, zone_id: TokenId) -> Option<DutchAuctionView> {
+        self.dutch_auctions.get(&zone_id).map(|auction| to_dutch_auction_view(zone_id, auction))
+    }
+
+    pub fn get_dutch_auctions(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<DutchAuctionView> {
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+        self.dutch_auctions
+            .iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .map(|(zone_id, auction)| to_dutch_auction_view(zone_id, auction))
+            .collect()
+    }
+}
+
+impl Contract {
+    fn internal_remove_dutch_auction(&mut self, zone_id: &TokenId, auction: &DutchAuction) {
+        self.dutch_auctions.remove(zone_id);
+        iterate_token_area(zone_id.clone(), auction.width, auction.height, |cell| -> bool {
+            self.dutch_auction_cells.remove(&cell);
+            true
+        });
+    }
+
+    /// Cells of a zone on Dutch auction can only be minted by buying the zone.
+    pub(crate) fn assert_not_reserved(&self, token_id: &TokenId) {
+        assert!(self.dutch_auction_cells.get(token_id).is_none(), "Cell is reserved for a zone auction");
+    }
+}
+
+fn to_dutch_auction_view(zone_id: TokenId, auction: DutchAuction) -> DutchAuctionView {
+    let current_price = U128(auction.price_at(env::block_timestamp()));
+    DutchAuctionView { zone_id, auction, current_price }
+}
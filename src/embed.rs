@@ -0,0 +1,170 @@
+use near_contract_standards::non_fungible_token::core::NonFungibleTokenReceiver;
+use near_contract_standards::non_fungible_token::{refund_deposit, TokenId};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json;
+use near_sdk::{
+    assert_one_yocto, env, ext_contract, is_promise_success, near_bindgen, AccountId, Gas, PromiseOrValue,
+};
+
+use crate::events::{NftEmbed, NftEmbedRelease};
+use crate::*;
+
+const GAS_FOR_NFT_TRANSFER: Gas = Gas(15_000_000_000_000);
+const GAS_FOR_RESOLVE_RELEASE: Gas = Gas(5_000_000_000_000);
+
+#[ext_contract(ext_nft)]
+trait ExternalNft {
+    fn nft_transfer(&mut self, receiver_id: AccountId, token_id: TokenId, approval_id: Option<u64>, memo: Option<String>);
+}
+
+#[ext_contract(ext_self)]
+trait EmbedResolver {
+    fn resolve_embedded_nft_release(&mut self, root_id: TokenId, embedded_nft: EmbeddedNft);
+}
+
+/// A token of another NEP-171 contract held in escrow to be shown on a block.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EmbeddedNft {
+    pub nft_contract_id: AccountId,
+    pub token_id: TokenId,
+    /// Account the token goes back to when it leaves escrow.
+    pub owner_id: AccountId,
+}
+
+/// Token of another NEP-171 contract the block owner agreed to embed in their block.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EmbedAuthorization {
+    pub nft_contract_id: AccountId,
+    pub token_id: TokenId,
+}
+
+/// `msg` of the `nft_transfer_call` embedding a token: `{"root_id": "7"}`.
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EmbedMsg {
+    root_id: TokenId,
+}
+
+#[near_bindgen]
+impl NonFungibleTokenReceiver for Contract {
+    /// Take a token of another collection into escrow and show it on the block named in `msg`.
+    /// The token is sent back unless the block owner authorized embedding this token of the calling
+    /// contract, its previous owner owns the block and the block shows no embedded token yet.
+    #[allow(unused_variables)]
+    fn nft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        previous_owner_id: AccountId,
+        token_id: TokenId,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        let nft_contract_id = env::predecessor_account_id();
+        if nft_contract_id == env::current_account_id() {
+            return PromiseOrValue::Value(true);
+        }
+        let root_id = match serde_json::from_str::<EmbedMsg>(&msg) {
+            Ok(embed_msg) => embed_msg.root_id,
+            Err(_) => return PromiseOrValue::Value(true),
+        };
+        if self.tokens.owner_by_id.get(&root_id).is_none()
+            || self.is_covered_token(&root_id)
+            || self.internal_block_owner(&root_id) != previous_owner_id
+            || self.embedded_nfts.get(&root_id).is_some()
+            || self.embed_authorizations.get(&root_id)
+                != Some(EmbedAuthorization { nft_contract_id: nft_contract_id.clone(), token_id: token_id.clone() })
+        {
+            return PromiseOrValue::Value(true);
+        }
+        self.embed_authorizations.remove(&root_id);
+
+        let embedded_nft = EmbeddedNft { nft_contract_id, token_id, owner_id: previous_owner_id };
+        self.embedded_nfts.insert(&root_id, &embedded_nft);
+        NftEmbed {
+            owner_id: &embedded_nft.owner_id,
+            root_id: &root_id,
+            nft_contract_id: &embedded_nft.nft_contract_id,
+            token_id: &embedded_nft.token_id,
+        }
+        .emit();
+
+        PromiseOrValue::Value(false)
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Allow token `token_id` of `nft_contract_id` to be embedded in the block rooted at `root_id`
+    /// with `nft_transfer_call`, replacing an earlier authorization. The attached deposit must
+    /// cover the storage used, the excess is refunded.
+    #[payable]
+    pub fn authorize_embed(&mut self, root_id: TokenId, nft_contract_id: AccountId, token_id: TokenId) {
+        let initial_storage_usage = env::storage_usage();
+        let owner_id = self.internal_block_owner(&root_id);
+        assert_eq!(owner_id, env::predecessor_account_id(), "No permission to authorize embed");
+        self.embed_authorizations.insert(&root_id, &EmbedAuthorization { nft_contract_id, token_id });
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage));
+    }
+
+    pub fn get_embed_authorization(&self, root_id: TokenId) -> Option<EmbedAuthorization> {
+        self.embed_authorizations.get(&root_id)
+    }
+
+    /// Send the token embedded in the block rooted at `root_id` back to the account that embedded it.
+    #[payable]
+    pub fn withdraw_embedded_nft(&mut self, root_id: TokenId) {
+        assert_one_yocto();
+        let embedded_nft = self.embedded_nfts.get(&root_id).unwrap_or_else(|| env::panic_str("No embedded token"));
+        assert_eq!(embedded_nft.owner_id, env::predecessor_account_id(), "No permission to withdraw");
+        self.internal_release_embedded_nft(&root_id);
+    }
+
+    pub fn get_embedded_nft(&self, root_id: TokenId) -> Option<EmbeddedNft> {
+        self.embedded_nfts.get(&root_id)
+    }
+
+    /// Put the token back in escrow if sending it to its owner failed, so it can be withdrawn again.
+    #[private]
+    pub fn resolve_embedded_nft_release(&mut self, root_id: TokenId, embedded_nft: EmbeddedNft) {
+        if !is_promise_success() && self.embedded_nfts.get(&root_id).is_none() {
+            self.embedded_nfts.insert(&root_id, &embedded_nft);
+        }
+    }
+}
+
+impl Contract {
+    /// Send the token embedded in the block rooted at `root_id`, if any, back to the account that
+    /// embedded it.
+    pub(crate) fn internal_release_embedded_nft(&mut self, root_id: &TokenId) {
+        let embedded_nft = match self.embedded_nfts.remove(root_id) {
+            Some(embedded_nft) => embedded_nft,
+            None => return,
+        };
+        NftEmbedRelease {
+            owner_id: &embedded_nft.owner_id,
+            root_id,
+            nft_contract_id: &embedded_nft.nft_contract_id,
+            token_id: &embedded_nft.token_id,
+        }
+        .emit();
+
+        ext_nft::nft_transfer(
+            embedded_nft.owner_id.clone(),
+            embedded_nft.token_id.clone(),
+            None,
+            Some("released from pixelland block".to_string()),
+            embedded_nft.nft_contract_id.clone(),
+            1,
+            GAS_FOR_NFT_TRANSFER,
+        )
+        .then(ext_self::resolve_embedded_nft_release(
+            root_id.clone(),
+            embedded_nft,
+            env::current_account_id(),
+            0,
+            GAS_FOR_RESOLVE_RELEASE,
+        ));
+    }
+}
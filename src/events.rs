@@ -0,0 +1,1482 @@
+//! Pixelland events, logged in the NEP-297 format so indexers can follow the state of the map:
+//! `EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"pixel_merge","data":[...]}`
+//!
+//! Log an event by calling `.emit()` on it, or `emit_many` to log several of the same kind at once.
+
+use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::Serialize;
+use near_sdk::serde_json;
+use near_sdk::{env, AccountId};
+
+use crate::{DutchAuction, MergeLimits, MergeRect, RentalTerms, RoyaltyConfig};
+
+pub const PIXELLAND_STANDARD: &str = "pixelland";
+pub const PIXELLAND_VERSION: &str = "1.0.0";
+
+/// Cells merged into the block rooted at `root_id`.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PixelMerge<'a> {
+    pub owner_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub rects: &'a [MergeRect],
+}
+
+impl PixelMerge<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[PixelMerge<'_>]) {
+        PixelLandEventKind::PixelMerge(data).emit()
+    }
+}
+
+/// Block rooted at `root_id` split back into single cells.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PixelUnmerge<'a> {
+    pub owner_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+}
+
+impl PixelUnmerge<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[PixelUnmerge<'_>]) {
+        PixelLandEventKind::PixelUnmerge(data).emit()
+    }
+}
+
+/// Cells of a block locked into the block token `block_token_id`.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlockLock<'a> {
+    pub owner_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub block_token_id: &'a TokenId,
+}
+
+impl BlockLock<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[BlockLock<'_>]) {
+        PixelLandEventKind::BlockLock(data).emit()
+    }
+}
+
+/// Block token `block_token_id` burnt and the cells of its block released to `owner_id`.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlockUnlock<'a> {
+    pub owner_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub block_token_id: &'a TokenId,
+}
+
+impl BlockUnlock<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[BlockUnlock<'_>]) {
+        PixelLandEventKind::BlockUnlock(data).emit()
+    }
+}
+
+/// Image of the block rooted at `root_id` set to `cid`.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PixelImageSet<'a> {
+    pub owner_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub cid: &'a str,
+    pub mime: &'a str,
+}
+
+impl PixelImageSet<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[PixelImageSet<'_>]) {
+        PixelLandEventKind::PixelImageSet(data).emit()
+    }
+}
+
+/// Link and caption of the block rooted at `root_id` set.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PixelLinkSet<'a> {
+    pub owner_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub url: &'a str,
+    pub caption: &'a str,
+}
+
+impl PixelLinkSet<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[PixelLinkSet<'_>]) {
+        PixelLandEventKind::PixelLinkSet(data).emit()
+    }
+}
+
+/// Link of the block rooted at `root_id` removed, e.g. because the block changed hands.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PixelLinkClear<'a> {
+    pub root_id: &'a TokenId,
+}
+
+impl PixelLinkClear<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[PixelLinkClear<'_>]) {
+        PixelLandEventKind::PixelLinkClear(data).emit()
+    }
+}
+
+/// Cells painted, `colors[i]` is the `0xRRGGBB` color of `token_ids[i]`.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PixelPaint<'a> {
+    pub painter_id: &'a AccountId,
+    pub token_ids: &'a [&'a str],
+    pub colors: &'a [u32],
+}
+
+impl PixelPaint<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[PixelPaint<'_>]) {
+        PixelLandEventKind::PixelPaint(data).emit()
+    }
+}
+
+/// Content of the block rooted at `root_id` reported by `flagger_id`.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlockFlag<'a> {
+    pub flagger_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub reason_code: u8,
+}
+
+impl BlockFlag<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[BlockFlag<'_>]) {
+        PixelLandEventKind::BlockFlag(data).emit()
+    }
+}
+
+/// Image and link of the block rooted at `root_id` hidden by a moderator.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlockHide<'a> {
+    pub moderator_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub reason_code: u8,
+}
+
+impl BlockHide<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[BlockHide<'_>]) {
+        PixelLandEventKind::BlockHide(data).emit()
+    }
+}
+
+/// Owner of the hidden block rooted at `root_id` asked for it to be restored.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlockAppeal<'a> {
+    pub owner_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+}
+
+impl BlockAppeal<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[BlockAppeal<'_>]) {
+        PixelLandEventKind::BlockAppeal(data).emit()
+    }
+}
+
+/// Content of the block rooted at `root_id` shown again by a moderator.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlockRestore<'a> {
+    pub moderator_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+}
+
+impl BlockRestore<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[BlockRestore<'_>]) {
+        PixelLandEventKind::BlockRestore(data).emit()
+    }
+}
+
+/// Moderation case of the block rooted at `root_id` closed without action.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ModerationDismiss<'a> {
+    pub moderator_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+}
+
+impl ModerationDismiss<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[ModerationDismiss<'_>]) {
+        PixelLandEventKind::ModerationDismiss(data).emit()
+    }
+}
+
+/// Token `token_id` of `nft_contract_id` taken into escrow to be shown on the block rooted at `root_id`.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftEmbed<'a> {
+    pub owner_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub nft_contract_id: &'a AccountId,
+    pub token_id: &'a TokenId,
+}
+
+impl NftEmbed<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[NftEmbed<'_>]) {
+        PixelLandEventKind::NftEmbed(data).emit()
+    }
+}
+
+/// Token embedded in the block rooted at `root_id` sent back to `owner_id`.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftEmbedRelease<'a> {
+    pub owner_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub nft_contract_id: &'a AccountId,
+    pub token_id: &'a TokenId,
+}
+
+impl NftEmbedRelease<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[NftEmbedRelease<'_>]) {
+        PixelLandEventKind::NftEmbedRelease(data).emit()
+    }
+}
+
+/// Block rooted at `root_id` offered for sale at `price`.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Listing<'a> {
+    pub seller_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub price: U128,
+}
+
+impl Listing<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[Listing<'_>]) {
+        PixelLandEventKind::Listing(data).emit()
+    }
+}
+
+/// Listing of the block rooted at `root_id` cancelled.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Delisting<'a> {
+    pub root_id: &'a TokenId,
+}
+
+impl Delisting<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[Delisting<'_>]) {
+        PixelLandEventKind::Delisting(data).emit()
+    }
+}
+
+/// Block rooted at `root_id` sold, `fee` of `price` went to the contract owner.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Sale<'a> {
+    pub seller_id: &'a AccountId,
+    pub buyer_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub price: U128,
+    pub fee: U128,
+}
+
+impl Sale<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[Sale<'_>]) {
+        PixelLandEventKind::Sale(data).emit()
+    }
+}
+
+/// Block rooted at `root_id` put on auction until `end_at`.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionCreate<'a> {
+    pub seller_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub reserve_price: U128,
+    pub end_at: U64,
+}
+
+impl AuctionCreate<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[AuctionCreate<'_>]) {
+        PixelLandEventKind::AuctionCreate(data).emit()
+    }
+}
+
+/// New highest bid on the block rooted at `root_id`, `end_at` includes any anti-sniping extension.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionBid<'a> {
+    pub bidder_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub amount: U128,
+    pub end_at: U64,
+}
+
+impl AuctionBid<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[AuctionBid<'_>]) {
+        PixelLandEventKind::AuctionBid(data).emit()
+    }
+}
+
+/// Auction of the block rooted at `root_id` closed, `winner_id` is absent when nobody bid.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionSettle<'a> {
+    pub seller_id: &'a AccountId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub winner_id: Option<&'a AccountId>,
+    pub root_id: &'a TokenId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<U128>,
+}
+
+impl AuctionSettle<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[AuctionSettle<'_>]) {
+        PixelLandEventKind::AuctionSettle(data).emit()
+    }
+}
+
+/// Auction of the block rooted at `root_id` cancelled by the seller.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionCancel<'a> {
+    pub root_id: &'a TokenId,
+}
+
+impl AuctionCancel<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[AuctionCancel<'_>]) {
+        PixelLandEventKind::AuctionCancel(data).emit()
+    }
+}
+
+/// Unminted zone from `zone_id` put on Dutch auction.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DutchAuctionCreate<'a> {
+    pub zone_id: &'a TokenId,
+    #[serde(flatten)]
+    pub auction: &'a DutchAuction,
+}
+
+impl DutchAuctionCreate<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[DutchAuctionCreate<'_>]) {
+        PixelLandEventKind::DutchAuctionCreate(data).emit()
+    }
+}
+
+/// Zone from `zone_id` bought at `price` and minted to `buyer_id`.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DutchAuctionSale<'a> {
+    pub buyer_id: &'a AccountId,
+    pub zone_id: &'a TokenId,
+    pub price: U128,
+}
+
+impl DutchAuctionSale<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[DutchAuctionSale<'_>]) {
+        PixelLandEventKind::DutchAuctionSale(data).emit()
+    }
+}
+
+/// Dutch auction of the zone from `zone_id` cancelled by the contract owner.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DutchAuctionCancel<'a> {
+    pub zone_id: &'a TokenId,
+}
+
+impl DutchAuctionCancel<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[DutchAuctionCancel<'_>]) {
+        PixelLandEventKind::DutchAuctionCancel(data).emit()
+    }
+}
+
+/// Escrowed offer on the block rooted at `root_id`, or on the unminted cell `root_id`.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OfferMake<'a> {
+    pub offer_id: U64,
+    pub buyer_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub amount: U128,
+    pub expires_at: U64,
+}
+
+impl OfferMake<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[OfferMake<'_>]) {
+        PixelLandEventKind::OfferMake(data).emit()
+    }
+}
+
+/// Offer executed, the block went to `buyer_id` and `fee` of `amount` to the contract owner.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OfferAccept<'a> {
+    pub offer_id: U64,
+    pub seller_id: &'a AccountId,
+    pub buyer_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub amount: U128,
+    pub fee: U128,
+}
+
+impl OfferAccept<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[OfferAccept<'_>]) {
+        PixelLandEventKind::OfferAccept(data).emit()
+    }
+}
+
+/// Offer withdrawn and refunded to its buyer.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OfferCancel<'a> {
+    pub offer_id: U64,
+    pub root_id: &'a TokenId,
+}
+
+impl OfferCancel<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[OfferCancel<'_>]) {
+        PixelLandEventKind::OfferCancel(data).emit()
+    }
+}
+
+/// Rental terms of the block rooted at `root_id` set, or removed when `terms` is missing.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RentalTermsSet<'a> {
+    pub owner_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub terms: Option<&'a RentalTerms>,
+}
+
+impl RentalTermsSet<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[RentalTermsSet<'_>]) {
+        PixelLandEventKind::RentalTermsSet(data).emit()
+    }
+}
+
+/// Block rooted at `root_id` rented by `renter_id` from `start_at` to `end_at` for `price`.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlockRent<'a> {
+    pub renter_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub price: U128,
+    pub start_at: U64,
+    pub end_at: U64,
+}
+
+impl BlockRent<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[BlockRent<'_>]) {
+        PixelLandEventKind::BlockRent(data).emit()
+    }
+}
+
+/// Booking of the block rooted at `root_id` starting at `start_at` cancelled, `refund` went back
+/// to the renter.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BookingCancel<'a> {
+    pub renter_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub start_at: U64,
+    pub refund: U128,
+}
+
+impl BookingCancel<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[BookingCancel<'_>]) {
+        PixelLandEventKind::BookingCancel(data).emit()
+    }
+}
+
+/// Harberger valuation of the block rooted at `root_id` declared by its owner.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ValuationSet<'a> {
+    pub owner_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub valuation: U128,
+}
+
+impl ValuationSet<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[ValuationSet<'_>]) {
+        PixelLandEventKind::ValuationSet(data).emit()
+    }
+}
+
+/// Block rooted at `root_id` force-bought from `owner_id` at its declared valuation.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ForceBuy<'a> {
+    pub owner_id: &'a AccountId,
+    pub buyer_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub price: U128,
+}
+
+impl ForceBuy<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[ForceBuy<'_>]) {
+        PixelLandEventKind::ForceBuy(data).emit()
+    }
+}
+
+/// Block rooted at `root_id` taken from a delinquent owner for the treasury.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlockSeize<'a> {
+    pub owner_id: &'a AccountId,
+    pub treasury_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+}
+
+impl BlockSeize<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[BlockSeize<'_>]) {
+        PixelLandEventKind::BlockSeize(data).emit()
+    }
+}
+
+/// Block rooted at `root_id` moved into a vault, `total_supply` shares of the NEP-141 token
+/// `token_account_id` minted to `owner_id`.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VaultCreate<'a> {
+    pub owner_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub token_account_id: &'a AccountId,
+    pub total_supply: U128,
+}
+
+impl VaultCreate<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[VaultCreate<'_>]) {
+        PixelLandEventKind::VaultCreate(data).emit()
+    }
+}
+
+/// `amount` shares locked by `voter_id` counted for the open image proposal of the vault of `root_id`.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VaultVote<'a> {
+    pub voter_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub amount: U128,
+}
+
+impl VaultVote<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[VaultVote<'_>]) {
+        PixelLandEventKind::VaultVote(data).emit()
+    }
+}
+
+/// Image proposed for the vaulted block rooted at `root_id`.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VaultImagePropose<'a> {
+    pub proposer_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub cid: &'a str,
+}
+
+impl VaultImagePropose<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[VaultImagePropose<'_>]) {
+        PixelLandEventKind::VaultImagePropose(data).emit()
+    }
+}
+
+/// Every share of the vault of `root_id` burnt by `owner_id`, who got the block back.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VaultRedeem<'a> {
+    pub owner_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+}
+
+impl VaultRedeem<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[VaultRedeem<'_>]) {
+        PixelLandEventKind::VaultRedeem(data).emit()
+    }
+}
+
+/// Block rooted at `root_id` staked with reward weight `weight`.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlockStake<'a> {
+    pub owner_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub weight: U128,
+}
+
+impl BlockStake<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[BlockStake<'_>]) {
+        PixelLandEventKind::BlockStake(data).emit()
+    }
+}
+
+/// Block rooted at `root_id` unstaked.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlockUnstake<'a> {
+    pub owner_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+}
+
+impl BlockUnstake<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[BlockUnstake<'_>]) {
+        PixelLandEventKind::BlockUnstake(data).emit()
+    }
+}
+
+/// Reward points earned by the staked block rooted at `root_id` credited to `owner_id`.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RewardClaim<'a> {
+    pub owner_id: &'a AccountId,
+    pub root_id: &'a TokenId,
+    pub amount: U128,
+}
+
+impl RewardClaim<'_> {
+    pub fn emit(self) {
+        Self::emit_many(&[self])
+    }
+
+    pub fn emit_many(data: &[RewardClaim<'_>]) {
+        PixelLandEventKind::RewardClaim(data).emit()
+    }
+}
+
+/// Mint price per cell changed.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriceUpdate {
+    pub old_price: U128,
+    pub new_price: U128,
+}
+
+impl PriceUpdate {
+    pub fn emit(self) {
+        PixelLandEventKind::PriceUpdate(&[self]).emit()
+    }
+}
+
+/// Merge limits replaced by the contract owner.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MergeLimitsUpdate<'a> {
+    pub merge_limits: &'a MergeLimits,
+}
+
+impl MergeLimitsUpdate<'_> {
+    pub fn emit(self) {
+        PixelLandEventKind::MergeLimitsUpdate(&[self]).emit()
+    }
+}
+
+/// Royalty of the collection, or of the block rooted at `root_id`, replaced by the contract
+/// owner. A missing `config` means the block falls back to the collection royalty.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoyaltyUpdate<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root_id: Option<&'a TokenId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<&'a RoyaltyConfig>,
+}
+
+impl RoyaltyUpdate<'_> {
+    pub fn emit(self) {
+        PixelLandEventKind::RoyaltyUpdate(&[self]).emit()
+    }
+}
+
+/// Yearly Harberger tax rate changed, in basis points.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct HarbergerTaxRateUpdate {
+    pub old_rate: u16,
+    pub new_rate: u16,
+}
+
+impl HarbergerTaxRateUpdate {
+    pub fn emit(self) {
+        PixelLandEventKind::HarbergerTaxRateUpdate(&[self]).emit()
+    }
+}
+
+/// Market cut of every sale changed, in basis points.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MarketFeeUpdate {
+    pub old_fee: u16,
+    pub new_fee: u16,
+}
+
+impl MarketFeeUpdate {
+    pub fn emit(self) {
+        PixelLandEventKind::MarketFeeUpdate(&[self]).emit()
+    }
+}
+
+/// Time between two paints of unminted cells by one account changed, in nanoseconds.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PaintCooldownUpdate {
+    pub old_cooldown: U64,
+    pub new_cooldown: U64,
+}
+
+impl PaintCooldownUpdate {
+    pub fn emit(self) {
+        PixelLandEventKind::PaintCooldownUpdate(&[self]).emit()
+    }
+}
+
+/// Reward points a staked cell earns per epoch changed.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RewardRateUpdate {
+    pub old_rate: U128,
+    pub new_rate: U128,
+}
+
+impl RewardRateUpdate {
+    pub fn emit(self) {
+        PixelLandEventKind::RewardRateUpdate(&[self]).emit()
+    }
+}
+
+/// `account_id` allowed to hide and restore block content by the contract owner.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ModeratorAdd<'a> {
+    pub account_id: &'a AccountId,
+}
+
+impl ModeratorAdd<'_> {
+    pub fn emit(self) {
+        PixelLandEventKind::ModeratorAdd(&[self]).emit()
+    }
+}
+
+/// `account_id` no longer a moderator.
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ModeratorRemove<'a> {
+    pub account_id: &'a AccountId,
+}
+
+impl ModeratorRemove<'_> {
+    pub fn emit(self) {
+        PixelLandEventKind::ModeratorRemove(&[self]).emit()
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+struct PixelLandEvent<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event_kind: PixelLandEventKind<'a>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data")]
+#[serde(rename_all = "snake_case")]
+enum PixelLandEventKind<'a> {
+    PixelMerge(&'a [PixelMerge<'a>]),
+    PixelUnmerge(&'a [PixelUnmerge<'a>]),
+    BlockLock(&'a [BlockLock<'a>]),
+    BlockUnlock(&'a [BlockUnlock<'a>]),
+    PixelImageSet(&'a [PixelImageSet<'a>]),
+    PixelLinkSet(&'a [PixelLinkSet<'a>]),
+    PixelLinkClear(&'a [PixelLinkClear<'a>]),
+    PixelPaint(&'a [PixelPaint<'a>]),
+    BlockFlag(&'a [BlockFlag<'a>]),
+    BlockHide(&'a [BlockHide<'a>]),
+    BlockAppeal(&'a [BlockAppeal<'a>]),
+    BlockRestore(&'a [BlockRestore<'a>]),
+    ModerationDismiss(&'a [ModerationDismiss<'a>]),
+    NftEmbed(&'a [NftEmbed<'a>]),
+    NftEmbedRelease(&'a [NftEmbedRelease<'a>]),
+    Listing(&'a [Listing<'a>]),
+    Delisting(&'a [Delisting<'a>]),
+    Sale(&'a [Sale<'a>]),
+    AuctionCreate(&'a [AuctionCreate<'a>]),
+    AuctionBid(&'a [AuctionBid<'a>]),
+    AuctionSettle(&'a [AuctionSettle<'a>]),
+    AuctionCancel(&'a [AuctionCancel<'a>]),
+    DutchAuctionCreate(&'a [DutchAuctionCreate<'a>]),
+    DutchAuctionSale(&'a [DutchAuctionSale<'a>]),
+    DutchAuctionCancel(&'a [DutchAuctionCancel<'a>]),
+    OfferMake(&'a [OfferMake<'a>]),
+    OfferAccept(&'a [OfferAccept<'a>]),
+    OfferCancel(&'a [OfferCancel<'a>]),
+    RentalTermsSet(&'a [RentalTermsSet<'a>]),
+    BlockRent(&'a [BlockRent<'a>]),
+    BookingCancel(&'a [BookingCancel<'a>]),
+    ValuationSet(&'a [ValuationSet<'a>]),
+    ForceBuy(&'a [ForceBuy<'a>]),
+    BlockSeize(&'a [BlockSeize<'a>]),
+    VaultCreate(&'a [VaultCreate<'a>]),
+    VaultVote(&'a [VaultVote<'a>]),
+    VaultImagePropose(&'a [VaultImagePropose<'a>]),
+    VaultRedeem(&'a [VaultRedeem<'a>]),
+    BlockStake(&'a [BlockStake<'a>]),
+    BlockUnstake(&'a [BlockUnstake<'a>]),
+    RewardClaim(&'a [RewardClaim<'a>]),
+    PriceUpdate(&'a [PriceUpdate]),
+    MergeLimitsUpdate(&'a [MergeLimitsUpdate<'a>]),
+    RoyaltyUpdate(&'a [RoyaltyUpdate<'a>]),
+    HarbergerTaxRateUpdate(&'a [HarbergerTaxRateUpdate]),
+    MarketFeeUpdate(&'a [MarketFeeUpdate]),
+    PaintCooldownUpdate(&'a [PaintCooldownUpdate]),
+    RewardRateUpdate(&'a [RewardRateUpdate]),
+    ModeratorAdd(&'a [ModeratorAdd<'a>]),
+    ModeratorRemove(&'a [ModeratorRemove<'a>]),
+}
+
+impl PixelLandEventKind<'_> {
+    fn emit(self) {
+        let event = PixelLandEvent {
+            standard: PIXELLAND_STANDARD,
+            version: PIXELLAND_VERSION,
+            event_kind: self,
+        };
+        // Events cannot fail to serialize so fine to panic on error
+        let json = serde_json::to_string(&event).unwrap_or_else(|_| env::abort());
+        env::log_str(&format!("EVENT_JSON:{}", json));
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils;
+
+    fn bob() -> AccountId {
+        AccountId::new_unchecked("bob".to_string())
+    }
+
+    #[test]
+    fn test_pixel_merge() {
+        let rects = vec![MergeRect { token_id: "7".to_string(), width: 2, height: 1 }];
+        PixelMerge { owner_id: &bob(), root_id: &"7".to_string(), rects: &rects }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"pixel_merge","data":[{"owner_id":"bob","root_id":"7","rects":[{"token_id":"7","width":2,"height":1}]}]}"#
+        );
+    }
+
+    #[test]
+    fn test_pixel_unmerge() {
+        PixelUnmerge::emit_many(&[
+            PixelUnmerge { owner_id: &bob(), root_id: &"7".to_string() },
+            PixelUnmerge { owner_id: &bob(), root_id: &"9".to_string() },
+        ]);
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"pixel_unmerge","data":[{"owner_id":"bob","root_id":"7"},{"owner_id":"bob","root_id":"9"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_block_lock_unlock() {
+        let root_id = "7".to_string();
+        let block_token_id = "block:7".to_string();
+        BlockLock { owner_id: &bob(), root_id: &root_id, block_token_id: &block_token_id }.emit();
+        BlockUnlock { owner_id: &bob(), root_id: &root_id, block_token_id: &block_token_id }.emit();
+        let logs = test_utils::get_logs();
+        assert_eq!(
+            logs[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"block_lock","data":[{"owner_id":"bob","root_id":"7","block_token_id":"block:7"}]}"#
+        );
+        assert_eq!(
+            logs[1],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"block_unlock","data":[{"owner_id":"bob","root_id":"7","block_token_id":"block:7"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_pixel_image_set() {
+        PixelImageSet { owner_id: &bob(), root_id: &"7".to_string(), cid: "bafy", mime: "image/png" }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"pixel_image_set","data":[{"owner_id":"bob","root_id":"7","cid":"bafy","mime":"image/png"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_pixel_link() {
+        let root_id = "7".to_string();
+        PixelLinkSet { owner_id: &bob(), root_id: &root_id, url: "https://near.org", caption: "near" }.emit();
+        PixelLinkClear { root_id: &root_id }.emit();
+        let logs = test_utils::get_logs();
+        assert_eq!(
+            logs[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"pixel_link_set","data":[{"owner_id":"bob","root_id":"7","url":"https://near.org","caption":"near"}]}"#
+        );
+        assert_eq!(
+            logs[1],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"pixel_link_clear","data":[{"root_id":"7"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_pixel_paint() {
+        PixelPaint { painter_id: &bob(), token_ids: &["0", "1"], colors: &[0xFF0000, 0x00FF00] }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"pixel_paint","data":[{"painter_id":"bob","token_ids":["0","1"],"colors":[16711680,65280]}]}"#
+        );
+    }
+
+    #[test]
+    fn test_moderation() {
+        let root_id = "7".to_string();
+        BlockFlag { flagger_id: &bob(), root_id: &root_id, reason_code: 2 }.emit();
+        BlockHide { moderator_id: &bob(), root_id: &root_id, reason_code: 2 }.emit();
+        BlockAppeal { owner_id: &bob(), root_id: &root_id }.emit();
+        BlockRestore { moderator_id: &bob(), root_id: &root_id }.emit();
+        ModerationDismiss { moderator_id: &bob(), root_id: &root_id }.emit();
+        let logs = test_utils::get_logs();
+        assert_eq!(
+            logs[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"block_flag","data":[{"flagger_id":"bob","root_id":"7","reason_code":2}]}"#
+        );
+        assert_eq!(
+            logs[1],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"block_hide","data":[{"moderator_id":"bob","root_id":"7","reason_code":2}]}"#
+        );
+        assert_eq!(
+            logs[2],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"block_appeal","data":[{"owner_id":"bob","root_id":"7"}]}"#
+        );
+        assert_eq!(
+            logs[3],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"block_restore","data":[{"moderator_id":"bob","root_id":"7"}]}"#
+        );
+        assert_eq!(
+            logs[4],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"moderation_dismiss","data":[{"moderator_id":"bob","root_id":"7"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_nft_embed() {
+        let root_id = "7".to_string();
+        let nft_contract_id = AccountId::new_unchecked("paras".to_string());
+        let token_id = "42".to_string();
+        NftEmbed { owner_id: &bob(), root_id: &root_id, nft_contract_id: &nft_contract_id, token_id: &token_id }.emit();
+        NftEmbedRelease { owner_id: &bob(), root_id: &root_id, nft_contract_id: &nft_contract_id, token_id: &token_id }.emit();
+        let logs = test_utils::get_logs();
+        assert_eq!(
+            logs[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"nft_embed","data":[{"owner_id":"bob","root_id":"7","nft_contract_id":"paras","token_id":"42"}]}"#
+        );
+        assert_eq!(
+            logs[1],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"nft_embed_release","data":[{"owner_id":"bob","root_id":"7","nft_contract_id":"paras","token_id":"42"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_market() {
+        let root_id = "7".to_string();
+        let alice = AccountId::new_unchecked("alice".to_string());
+        Listing { seller_id: &bob(), root_id: &root_id, price: U128(100) }.emit();
+        Delisting { root_id: &root_id }.emit();
+        Sale { seller_id: &bob(), buyer_id: &alice, root_id: &root_id, price: U128(100), fee: U128(2) }.emit();
+        let logs = test_utils::get_logs();
+        assert_eq!(
+            logs[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"listing","data":[{"seller_id":"bob","root_id":"7","price":"100"}]}"#
+        );
+        assert_eq!(
+            logs[1],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"delisting","data":[{"root_id":"7"}]}"#
+        );
+        assert_eq!(
+            logs[2],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"sale","data":[{"seller_id":"bob","buyer_id":"alice","root_id":"7","price":"100","fee":"2"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_auction() {
+        let root_id = "7".to_string();
+        let alice = AccountId::new_unchecked("alice".to_string());
+        AuctionCreate { seller_id: &bob(), root_id: &root_id, reserve_price: U128(100), end_at: U64(5) }.emit();
+        AuctionBid { bidder_id: &alice, root_id: &root_id, amount: U128(120), end_at: U64(6) }.emit();
+        AuctionSettle { seller_id: &bob(), winner_id: Some(&alice), root_id: &root_id, price: Some(U128(120)) }.emit();
+        AuctionSettle { seller_id: &bob(), winner_id: None, root_id: &root_id, price: None }.emit();
+        AuctionCancel { root_id: &root_id }.emit();
+        let logs = test_utils::get_logs();
+        assert_eq!(
+            logs[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"auction_create","data":[{"seller_id":"bob","root_id":"7","reserve_price":"100","end_at":"5"}]}"#
+        );
+        assert_eq!(
+            logs[1],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"auction_bid","data":[{"bidder_id":"alice","root_id":"7","amount":"120","end_at":"6"}]}"#
+        );
+        assert_eq!(
+            logs[2],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"auction_settle","data":[{"seller_id":"bob","winner_id":"alice","root_id":"7","price":"120"}]}"#
+        );
+        assert_eq!(
+            logs[3],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"auction_settle","data":[{"seller_id":"bob","root_id":"7"}]}"#
+        );
+        assert_eq!(
+            logs[4],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"auction_cancel","data":[{"root_id":"7"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_dutch_auction() {
+        let zone_id = "7".to_string();
+        let auction = DutchAuction {
+            width: 3,
+            height: 2,
+            start_price: U128(1000),
+            floor_price: U128(100),
+            start_at: U64(10),
+            end_at: U64(20),
+        };
+        DutchAuctionCreate { zone_id: &zone_id, auction: &auction }.emit();
+        DutchAuctionSale { buyer_id: &bob(), zone_id: &zone_id, price: U128(550) }.emit();
+        DutchAuctionCancel { zone_id: &zone_id }.emit();
+        let logs = test_utils::get_logs();
+        assert_eq!(
+            logs[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"dutch_auction_create","data":[{"zone_id":"7","width":3,"height":2,"start_price":"1000","floor_price":"100","start_at":"10","end_at":"20"}]}"#
+        );
+        assert_eq!(
+            logs[1],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"dutch_auction_sale","data":[{"buyer_id":"bob","zone_id":"7","price":"550"}]}"#
+        );
+        assert_eq!(
+            logs[2],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"dutch_auction_cancel","data":[{"zone_id":"7"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_offer() {
+        let root_id = "7".to_string();
+        let alice = AccountId::new_unchecked("alice".to_string());
+        OfferMake { offer_id: U64(0), buyer_id: &bob(), root_id: &root_id, amount: U128(100), expires_at: U64(5) }.emit();
+        OfferAccept {
+            offer_id: U64(0),
+            seller_id: &alice,
+            buyer_id: &bob(),
+            root_id: &root_id,
+            amount: U128(100),
+            fee: U128(2),
+        }
+        .emit();
+        OfferCancel { offer_id: U64(1), root_id: &root_id }.emit();
+        let logs = test_utils::get_logs();
+        assert_eq!(
+            logs[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"offer_make","data":[{"offer_id":"0","buyer_id":"bob","root_id":"7","amount":"100","expires_at":"5"}]}"#
+        );
+        assert_eq!(
+            logs[1],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"offer_accept","data":[{"offer_id":"0","seller_id":"alice","buyer_id":"bob","root_id":"7","amount":"100","fee":"2"}]}"#
+        );
+        assert_eq!(
+            logs[2],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"offer_cancel","data":[{"offer_id":"1","root_id":"7"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_rental() {
+        let root_id = "7".to_string();
+        let alice = AccountId::new_unchecked("alice".to_string());
+        let terms = RentalTerms { price_per_day: U128(10), min_days: 1, max_days: 7 };
+        RentalTermsSet { owner_id: &bob(), root_id: &root_id, terms: Some(&terms) }.emit();
+        RentalTermsSet { owner_id: &bob(), root_id: &root_id, terms: None }.emit();
+        BlockRent { renter_id: &alice, root_id: &root_id, price: U128(20), start_at: U64(1), end_at: U64(2) }.emit();
+        BookingCancel { renter_id: &alice, root_id: &root_id, start_at: U64(1), refund: U128(10) }.emit();
+        let logs = test_utils::get_logs();
+        assert_eq!(
+            logs[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"rental_terms_set","data":[{"owner_id":"bob","root_id":"7","terms":{"price_per_day":"10","min_days":1,"max_days":7}}]}"#
+        );
+        assert_eq!(
+            logs[1],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"rental_terms_set","data":[{"owner_id":"bob","root_id":"7"}]}"#
+        );
+        assert_eq!(
+            logs[2],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"block_rent","data":[{"renter_id":"alice","root_id":"7","price":"20","start_at":"1","end_at":"2"}]}"#
+        );
+        assert_eq!(
+            logs[3],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"booking_cancel","data":[{"renter_id":"alice","root_id":"7","start_at":"1","refund":"10"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_harberger() {
+        let root_id = "7".to_string();
+        let alice = AccountId::new_unchecked("alice".to_string());
+        ValuationSet { owner_id: &bob(), root_id: &root_id, valuation: U128(100) }.emit();
+        ForceBuy { owner_id: &bob(), buyer_id: &alice, root_id: &root_id, price: U128(100) }.emit();
+        BlockSeize { owner_id: &alice, treasury_id: &bob(), root_id: &root_id }.emit();
+        let logs = test_utils::get_logs();
+        assert_eq!(
+            logs[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"valuation_set","data":[{"owner_id":"bob","root_id":"7","valuation":"100"}]}"#
+        );
+        assert_eq!(
+            logs[1],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"force_buy","data":[{"owner_id":"bob","buyer_id":"alice","root_id":"7","price":"100"}]}"#
+        );
+        assert_eq!(
+            logs[2],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"block_seize","data":[{"owner_id":"alice","treasury_id":"bob","root_id":"7"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_vault() {
+        let root_id = "7".to_string();
+        let alice = AccountId::new_unchecked("alice".to_string());
+        let token_account_id = AccountId::new_unchecked("v7.pixelland".to_string());
+        VaultCreate { owner_id: &bob(), root_id: &root_id, token_account_id: &token_account_id, total_supply: U128(100) }.emit();
+        VaultVote { voter_id: &alice, root_id: &root_id, amount: U128(40) }.emit();
+        VaultImagePropose { proposer_id: &alice, root_id: &root_id, cid: "bafy" }.emit();
+        VaultRedeem { owner_id: &bob(), root_id: &root_id }.emit();
+        let logs = test_utils::get_logs();
+        assert_eq!(
+            logs[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"vault_create","data":[{"owner_id":"bob","root_id":"7","token_account_id":"v7.pixelland","total_supply":"100"}]}"#
+        );
+        assert_eq!(
+            logs[1],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"vault_vote","data":[{"voter_id":"alice","root_id":"7","amount":"40"}]}"#
+        );
+        assert_eq!(
+            logs[2],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"vault_image_propose","data":[{"proposer_id":"alice","root_id":"7","cid":"bafy"}]}"#
+        );
+        assert_eq!(
+            logs[3],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"vault_redeem","data":[{"owner_id":"bob","root_id":"7"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_staking() {
+        let root_id = "7".to_string();
+        BlockStake { owner_id: &bob(), root_id: &root_id, weight: U128(20000) }.emit();
+        RewardClaim { owner_id: &bob(), root_id: &root_id, amount: U128(200) }.emit();
+        BlockUnstake { owner_id: &bob(), root_id: &root_id }.emit();
+        let logs = test_utils::get_logs();
+        assert_eq!(
+            logs[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"block_stake","data":[{"owner_id":"bob","root_id":"7","weight":"20000"}]}"#
+        );
+        assert_eq!(
+            logs[1],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"reward_claim","data":[{"owner_id":"bob","root_id":"7","amount":"200"}]}"#
+        );
+        assert_eq!(
+            logs[2],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"block_unstake","data":[{"owner_id":"bob","root_id":"7"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_price_update() {
+        PriceUpdate { old_price: U128(1), new_price: U128(2) }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"price_update","data":[{"old_price":"1","new_price":"2"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_merge_limits_update() {
+        let merge_limits = MergeLimits { max_area: 4, max_width: 2, max_height: 2, max_aspect_ratio: 1 };
+        MergeLimitsUpdate { merge_limits: &merge_limits }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"merge_limits_update","data":[{"merge_limits":{"max_area":4,"max_width":2,"max_height":2,"max_aspect_ratio":1}}]}"#
+        );
+    }
+
+    #[test]
+    fn test_royalty_update() {
+        let root_id = "7".to_string();
+        let config = RoyaltyConfig { treasury_id: bob(), minter_bps: 250, treasury_bps: 100 };
+        RoyaltyUpdate { root_id: None, config: Some(&config) }.emit();
+        RoyaltyUpdate { root_id: Some(&root_id), config: None }.emit();
+        let logs = test_utils::get_logs();
+        assert_eq!(
+            logs[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"royalty_update","data":[{"config":{"treasury_id":"bob","minter_bps":250,"treasury_bps":100}}]}"#
+        );
+        assert_eq!(
+            logs[1],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"royalty_update","data":[{"root_id":"7"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_harberger_tax_rate_update() {
+        HarbergerTaxRateUpdate { old_rate: 0, new_rate: 1000 }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"harberger_tax_rate_update","data":[{"old_rate":0,"new_rate":1000}]}"#
+        );
+    }
+
+    #[test]
+    fn test_setting_updates() {
+        MarketFeeUpdate { old_fee: 0, new_fee: 250 }.emit();
+        PaintCooldownUpdate { old_cooldown: U64(0), new_cooldown: U64(60) }.emit();
+        RewardRateUpdate { old_rate: U128(1), new_rate: U128(2) }.emit();
+        let logs = test_utils::get_logs();
+        assert_eq!(
+            logs[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"market_fee_update","data":[{"old_fee":0,"new_fee":250}]}"#
+        );
+        assert_eq!(
+            logs[1],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"paint_cooldown_update","data":[{"old_cooldown":"0","new_cooldown":"60"}]}"#
+        );
+        assert_eq!(
+            logs[2],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"reward_rate_update","data":[{"old_rate":"1","new_rate":"2"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_moderator_update() {
+        ModeratorAdd { account_id: &bob() }.emit();
+        ModeratorRemove { account_id: &bob() }.emit();
+        let logs = test_utils::get_logs();
+        assert_eq!(
+            logs[0],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"moderator_add","data":[{"account_id":"bob"}]}"#
+        );
+        assert_eq!(
+            logs[1],
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"moderator_remove","data":[{"account_id":"bob"}]}"#
+        );
+    }
+}
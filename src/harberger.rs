@@ -0,0 +1,326 @@
+use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, near_bindgen, Balance, Promise};
+
+use crate::events::{BlockSeize, ForceBuy, HarbergerTaxRateUpdate, ValuationSet};
+use crate::market::FEE_DENOMINATOR;
+use crate::rental::DAY;
+use crate::*;
+
+/// Tax rates are yearly.
+pub const YEAR: u64 = 365 * DAY;
+/// Scale of the tax accumulator.
+const ACC_PRECISION: u128 = 1_000_000_000_000;
+
+/// Global tax accumulator: share of a valuation owed as tax since the mode started, scaled by
+/// `ACC_PRECISION`. A rate change only applies from the time it is made.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct TaxAccumulator {
+    pub acc_tax_per_valuation: u128,
+    pub updated_at: u64,
+}
+
+/// Self-assessed valuation of a block under Harberger tax and the deposit the tax is taken from.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct HarbergerAccount {
+    /// Account that declared the valuation and gets the deposit back.
+    pub owner_id: AccountId,
+    pub valuation: U128,
+    pub deposit: U128,
+    /// Time the tax was last taken from the deposit.
+    pub accrued_at: U64,
+    /// Tax accumulator value the tax was last taken at.
+    pub acc_at: U128,
+    /// Set once the deposit ran out, the block can then be seized for the treasury.
+    pub delinquent: bool,
+}
+
+impl HarbergerAccount {
+    /// Tax owed up to the tax accumulator value `acc` since the last accrual.
+    pub fn tax_due(&self, acc: u128) -> Balance {
+        let owed = acc - self.acc_at.0;
+        // split to keep `valuation * owed` from overflowing
+        self.valuation.0 / ACC_PRECISION * owed + self.valuation.0 % ACC_PRECISION * owed / ACC_PRECISION
+    }
+
+    /// Take the tax owed up to the accumulator value `acc`, reached at `timestamp`, from the
+    /// deposit, returns the amount taken.
+    fn accrue(&mut self, acc: u128, timestamp: u64) -> Balance {
+        let due = self.tax_due(acc);
+        let paid = due.min(self.deposit.0);
+        self.deposit = U128(self.deposit.0 - paid);
+        self.delinquent |= due > paid;
+        self.accrued_at = U64(timestamp);
+        self.acc_at = U128(acc);
+        paid
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Owner only, set the yearly Harberger tax rate in basis points. 0 turns the mode off:
+    /// no new valuations can be declared, existing ones stop accruing tax. Tax owed so far is kept
+    /// at the previous rate.
+    pub fn set_harberger_tax_rate(&mut self, tax_rate: u16) {
+        assert_eq!(env::predecessor_account_id(), self.tokens.owner_id, "Unauthorized");
+        assert!(tax_rate as u128 <= FEE_DENOMINATOR, "Invalid tax rate");
+        self.internal_update_tax_accumulator();
+        HarbergerTaxRateUpdate { old_rate: self.harberger_tax_rate, new_rate: tax_rate }.emit();
+        self.harberger_tax_rate = tax_rate;
+    }
+
+    pub fn get_harberger_tax_rate(&self) -> u16 {
+        self.harberger_tax_rate
+    }
+
+    /// Declare the price anyone can force-buy the block rooted at `root_id` at. The attached
+    /// deposit, minus the storage used, is added to the deposit the tax is taken from.
+    #[payable]
+    pub fn declare_valuation(&mut self, root_id: TokenId, valuation: U128) {
+        assert!(self.harberger_tax_rate > 0, "Harberger mode is off");
+        let initial_storage_usage = env::storage_usage();
+        let owner_id = self.internal_block_owner(&root_id);
+        assert_eq!(owner_id, env::predecessor_account_id(), "No permission to declare valuation");
+        assert!(valuation.0 > 0, "Valuation must be positive");
+
+        let mut account = self.internal_accrue_tax(&root_id).unwrap_or(HarbergerAccount {
+            owner_id: owner_id.clone(),
+            valuation,
+            deposit: U128(0),
+            accrued_at: U64(env::block_timestamp()),
+            acc_at: U128(self.internal_acc_tax_per_valuation()),
+            delinquent: false,
+        });
+        assert!(!account.delinquent, "Block is delinquent");
+        account.valuation = valuation;
+        self.harberger_accounts.insert(&root_id, &account);
+
+        let storage_cost = Balance::from(env::storage_usage().saturating_sub(initial_storage_usage)) * env::storage_byte_cost();
+        let deposit = env::attached_deposit();
+        assert!(deposit >= storage_cost, "Must attach {} yoctoNEAR to cover storage", storage_cost);
+        account.deposit = U128(account.deposit.0 + deposit - storage_cost);
+        self.harberger_accounts.insert(&root_id, &account);
+
+        ValuationSet { owner_id: &owner_id, root_id: &root_id, valuation }.emit();
+    }
+
+    /// Add the attached deposit to the tax deposit of the block rooted at `root_id`.
+    #[payable]
+    pub fn deposit_tax(&mut self, root_id: TokenId) {
+        let mut account = self.internal_accrue_tax(&root_id).unwrap_or_else(|| env::panic_str("Block has no valuation"));
+        assert!(!account.delinquent, "Block is delinquent");
+        account.deposit = U128(account.deposit.0 + env::attached_deposit());
+        self.harberger_accounts.insert(&root_id, &account);
+    }
+
+    /// Take `amount` back from the tax deposit of the block rooted at `root_id`.
+    #[payable]
+    pub fn withdraw_tax_deposit(&mut self, root_id: TokenId, amount: U128) {
+        assert_one_yocto();
+        let owner_id = self.internal_block_owner(&root_id);
+        assert_eq!(owner_id, env::predecessor_account_id(), "No permission to withdraw deposit");
+        let mut account = self.internal_accrue_tax(&root_id).unwrap_or_else(|| env::panic_str("Block has no valuation"));
+        assert!(amount.0 <= account.deposit.0, "Not enough deposit");
+        account.deposit = U128(account.deposit.0 - amount.0);
+        self.harberger_accounts.insert(&root_id, &account);
+        Promise::new(owner_id).transfer(amount.0);
+    }
+
+    /// Buy the block rooted at `root_id` at its declared valuation without the owner's consent,
+    /// cancelling its auction and unstaking it if needed. While the mode is on, a block without a
+    /// declared valuation is bought at its default valuation. The owner gets the price and what is
+    /// left of their tax deposit. The buyer declares `valuation` and the attached deposit beyond the
+    /// price and the storage used becomes their tax deposit.
+    #[payable]
+    pub fn force_buy(&mut self, root_id: TokenId, valuation: U128) {
+        assert!(self.vaults.get(&root_id).is_none(), "Block is in a vault");
+        let initial_storage_usage = env::storage_usage();
+        let (price, owner_deposit) = match self.internal_accrue_tax(&root_id) {
+            Some(account) => {
+                assert!(!account.delinquent, "Block is delinquent");
+                (account.valuation.0, account.deposit.0)
+            }
+            None => {
+                assert!(self.harberger_tax_rate > 0, "Block has no valuation");
+                (self.internal_default_valuation(&root_id), 0)
+            }
+        };
+        assert!(valuation.0 > 0, "Valuation must be positive");
+        let buyer_id = env::predecessor_account_id();
+        let owner_id = self.internal_block_owner(&root_id);
+        assert_ne!(buyer_id, owner_id, "Owner cannot force-buy own block");
+        let deposit = env::attached_deposit();
+        assert!(deposit >= price, "Not enough attached to buy");
+
+        self.harberger_accounts.remove(&root_id);
+        self.internal_cancel_auction(&root_id);
+        self.internal_unstake(&root_id);
+        self.internal_transfer_block(&root_id, &owner_id, &buyer_id, "force buy");
+        let mut account = HarbergerAccount {
+            owner_id: buyer_id.clone(),
+            valuation,
+            deposit: U128(0),
+            accrued_at: U64(env::block_timestamp()),
+            acc_at: U128(self.internal_acc_tax_per_valuation()),
+            delinquent: false,
+        };
+        self.harberger_accounts.insert(&root_id, &account);
+        let storage_cost = Balance::from(env::storage_usage().saturating_sub(initial_storage_usage)) * env::storage_byte_cost();
+        assert!(deposit - price >= storage_cost, "Must attach {} yoctoNEAR to cover the price and storage", price + storage_cost);
+        account.deposit = U128(deposit - price - storage_cost);
+        self.harberger_accounts.insert(&root_id, &account);
+        Promise::new(owner_id.clone()).transfer(price + owner_deposit);
+
+        ForceBuy { owner_id: &owner_id, buyer_id: &buyer_id, root_id: &root_id, price: U128(price) }.emit();
+    }
+
+    /// Hand the block rooted at `root_id` over to the treasury once its owner can no longer pay
+    /// the tax, cancelling its auction and unstaking it if needed. Callable by anyone.
+    pub fn seize_block(&mut self, root_id: TokenId) {
+        assert!(self.vaults.get(&root_id).is_none(), "Block is in a vault");
+        let account = self.internal_accrue_tax(&root_id).unwrap_or_else(|| env::panic_str("Block has no valuation"));
+        assert!(account.delinquent, "Block is not delinquent");
+        let owner_id = self.internal_block_owner(&root_id);
+        let treasury_id = self.royalty.treasury_id.clone();
+
+        self.harberger_accounts.remove(&root_id);
+        self.internal_cancel_auction(&root_id);
+        self.internal_unstake(&root_id);
+        if owner_id != treasury_id {
+            self.internal_transfer_block(&root_id, &owner_id, &treasury_id, "seize");
+        }
+        BlockSeize { owner_id: &owner_id, treasury_id: &treasury_id, root_id: &root_id }.emit();
+    }
+
+    /// Owner only, send the tax collected so far to the treasury.
+    pub fn withdraw_harberger_tax(&mut self) -> U128 {
+        assert_eq!(env::predecessor_account_id(), self.tokens.owner_id, "Unauthorized");
+        let collected = std::mem::take(&mut self.harberger_tax_collected);
+        if collected > 0 {
+            Promise::new(self.royalty.treasury_id.clone()).transfer(collected);
+        }
+        U128(collected)
+    }
+
+    /// Price the block rooted at `root_id` can be force-bought at: the declared valuation, or the
+    /// default valuation while the mode is on and none was declared. Vaulted blocks cannot be
+    /// force-bought.
+    pub fn get_block_valuation(&self, root_id: TokenId) -> Option<U128> {
+        match self.harberger_accounts.get(&root_id) {
+            Some(account) => Some(account.valuation),
+            None if self.harberger_tax_rate > 0 && self.vaults.get(&root_id).is_none() => Some(U128(self.internal_default_valuation(&root_id))),
+            None => None,
+        }
+    }
+
+    /// Harberger account of the block rooted at `root_id` with the tax owed up to now taken.
+    pub fn get_harberger_account(&self, root_id: TokenId) -> Option<HarbergerAccount> {
+        let mut account = self.harberger_accounts.get(&root_id)?;
+        account.accrue(self.internal_acc_tax_per_valuation(), env::block_timestamp());
+        Some(account)
+    }
+}
+
+impl Contract {
+    /// Take the tax owed by the block rooted at `root_id` and store the updated account.
+    pub(crate) fn internal_accrue_tax(&mut self, root_id: &TokenId) -> Option<HarbergerAccount> {
+        let mut account = self.harberger_accounts.get(root_id)?;
+        self.internal_update_tax_accumulator();
+        let acc = self.harberger_accumulator.acc_tax_per_valuation;
+        self.harberger_tax_collected += account.accrue(acc, env::block_timestamp());
+        self.harberger_accounts.insert(root_id, &account);
+        Some(account)
+    }
+
+    /// Accumulator value at the current block time.
+    fn internal_acc_tax_per_valuation(&self) -> u128 {
+        let accumulator = &self.harberger_accumulator;
+        let elapsed = env::block_timestamp().saturating_sub(accumulator.updated_at) as u128;
+        accumulator.acc_tax_per_valuation
+            + self.harberger_tax_rate as u128 * elapsed * ACC_PRECISION / (FEE_DENOMINATOR * YEAR as u128)
+    }
+
+    fn internal_update_tax_accumulator(&mut self) {
+        self.harberger_accumulator = TaxAccumulator {
+            acc_tax_per_valuation: self.internal_acc_tax_per_valuation(),
+            updated_at: env::block_timestamp(),
+        };
+    }
+
+    /// Valuation of a block nobody declared one for: the mint price of its cells, so holding land
+    /// without declaring never shields it from a force-buy.
+    pub(crate) fn internal_default_valuation(&self, root_id: &TokenId) -> Balance {
+        self.mint_price * self.get_block_cells(root_id.clone()).len() as Balance
+    }
+
+    /// Close the account of the block rooted at `root_id` once the block left the account that
+    /// declared the valuation: the tax owed is taken and the rest of the deposit goes back to the
+    /// declaring account. The new owner starts without a valuation.
+    pub(crate) fn internal_close_harberger(&mut self, root_id: &TokenId) {
+        match self.harberger_accounts.get(root_id) {
+            Some(account) if account.owner_id != self.internal_block_owner(root_id) => (),
+            _ => return,
+        }
+        let account = self.internal_accrue_tax(root_id).unwrap();
+        assert!(!account.delinquent, "Block is delinquent");
+        self.harberger_accounts.remove(root_id);
+        if account.deposit.0 > 0 {
+            Promise::new(account.owner_id).transfer(account.deposit.0);
+        }
+    }
+
+    /// Fold the accounts of the cells merged into the block rooted at `root_id` into one account,
+    /// valuations and deposits add up.
+    pub(crate) fn internal_merge_harberger(&mut self, root_id: &TokenId, cells: &[TokenId]) {
+        let mut merged: Option<HarbergerAccount> = None;
+        for cell in cells {
+            if let Some(account) = self.internal_accrue_tax(cell) {
+                self.harberger_accounts.remove(cell);
+                merged = Some(match merged {
+                    Some(merged) => HarbergerAccount {
+                        owner_id: merged.owner_id,
+                        valuation: U128(merged.valuation.0 + account.valuation.0),
+                        deposit: U128(merged.deposit.0 + account.deposit.0),
+                        accrued_at: account.accrued_at,
+                        acc_at: account.acc_at,
+                        delinquent: merged.delinquent || account.delinquent,
+                    },
+                    None => account,
+                });
+            }
+        }
+        if let Some(account) = merged {
+            assert!(!account.delinquent, "Block is delinquent");
+            self.harberger_accounts.insert(root_id, &account);
+        }
+    }
+
+    /// Split the account of the unmerged block rooted at `root_id` evenly over its cells, the
+    /// root keeps the rounding remainder.
+    pub(crate) fn internal_split_harberger(&mut self, root_id: &TokenId, cells: &[TokenId]) {
+        let account = match self.internal_accrue_tax(root_id) {
+            Some(account) => account,
+            None => return,
+        };
+        assert!(!account.delinquent, "Block is delinquent");
+        let count = cells.len() as u128;
+        for cell in cells {
+            let (mut valuation, mut deposit) = (account.valuation.0 / count, account.deposit.0 / count);
+            if cell == root_id {
+                valuation += account.valuation.0 % count;
+                deposit += account.deposit.0 % count;
+            }
+            self.harberger_accounts.insert(cell, &HarbergerAccount {
+                owner_id: account.owner_id.clone(),
+                valuation: U128(valuation.max(1)),
+                deposit: U128(deposit),
+                accrued_at: account.accrued_at,
+                acc_at: account.acc_at,
+                delinquent: false,
+            });
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use near_contract_standards::non_fungible_token::{refund_deposit, TokenId};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{Base64VecU8, U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId};
+
+use crate::events::PixelImageSet;
+use crate::*;
+
+const MAX_CID_LEN: usize = 128;
+const MAX_MIME_LEN: usize = 64;
+/// Length of a sha256 digest.
+pub const MEDIA_HASH_LEN: usize = 32;
+/// Number of past images kept per block.
+pub const IMAGE_HISTORY_LEN: usize = 10;
+
+/// Image shown on a block, stored under the block root.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlockImage {
+    /// IPFS CID of the image.
+    pub cid: String,
+    pub mime: String,
+    /// sha256 of the image bytes, the on-chain commitment to the content behind `cid`.
+    pub hash: Base64VecU8,
+}
+
+impl BlockImage {
+    pub fn assert_valid(&self) {
+        assert!(!self.cid.is_empty() && self.cid.len() <= MAX_CID_LEN, "Invalid cid");
+        assert!(self.mime.starts_with("image/") && self.mime.len() <= MAX_MIME_LEN, "Invalid mime type");
+        assert_eq!(self.hash.0.len(), MEDIA_HASH_LEN, "Hash must be a sha256 digest");
+    }
+}
+
+/// One entry of the image history of a block.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ImageVersion {
+    pub version: u32,
+    pub image: BlockImage,
+    pub set_by: AccountId,
+    pub set_at: U64,
+}
+
+/// The last `IMAGE_HISTORY_LEN` images of a block, oldest first.
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct ImageHistory {
+    next_version: u32,
+    versions: Vec<ImageVersion>,
+}
+
+/// Image of a block together with its bounding box, in the shape of the app's `PixelImage`.
+/// `pixel_id` is the top-left cell of the bounding box, which is the root only for blocks whose
+/// first rectangle is the top-left one.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "camelCase")]
+pub struct PixelImage {
+    pub pixel_id: TokenId,
+    pub root_id: TokenId,
+    pub cid: String,
+    pub mime: String,
+    pub hash: Base64VecU8,
+    pub w: u64,
+    pub h: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Set the image of the block rooted at `root_id`, a single cell is a 1x1 block.
+    /// `hash` is the sha256 of the image bytes. Only the block owner, or the renter while the block
+    /// is rented, can set it. The attached deposit must cover the storage used, the excess is refunded.
+    #[payable]
+    pub fn set_block_image(&mut self, root_id: TokenId, cid: String, mime: String, hash: Base64VecU8) {
+        let initial_storage_usage = env::storage_usage();
+        let owner_id = env::predecessor_account_id();
+        let renting = self.is_active_renter(&root_id, &owner_id);
+        if !renting {
+            assert_eq!(self.internal_block_owner(&root_id), owner_id, "No permission to set image");
+        }
+
+        let image = BlockImage { cid, mime, hash };
+        image.assert_valid();
+        if renting {
+            self.internal_set_rental_image(&root_id, image, &owner_id);
+        } else {
+            self.internal_set_block_image(&root_id, image, &owner_id);
+        }
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage));
+    }
+
+    /// Show again the image `version` from the history of the block rooted at `root_id`.
+    /// The restored image is recorded as a new version.
+    #[payable]
+    pub fn restore_block_image(&mut self, root_id: TokenId, version: u32) {
+        let initial_storage_usage = env::storage_usage();
+        let owner_id = self.internal_block_owner(&root_id);
+        assert_eq!(owner_id, env::predecessor_account_id(), "No permission to set image");
+
+        let image = self.image_history.get(&root_id)
+            .and_then(|history| history.versions.into_iter().find(|entry| entry.version == version))
+            .map(|entry| entry.image)
+            .unwrap_or_else(|| env::panic_str("Image version not found"));
+        self.internal_set_block_image(&root_id, image, &owner_id);
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage));
+    }
+
+    /// Whether `content` is the image committed to by the block rooted at `root_id`, for
+    /// renderers to check the bytes fetched from IPFS.
+    pub fn verify_block_media(&self, root_id: TokenId, content: Base64VecU8) -> bool {
+        match self.internal_block_image(&root_id) {
+            Some(image) => env::sha256(&content.0) == image.hash.0,
+            None => false
+        }
+    }
+
+    /// Past images of the block rooted at `root_id`, oldest first, empty while the block is hidden
+    /// by a moderator.
+    pub fn get_block_image_history(&self, root_id: TokenId, from_index: Option<U128>, limit: Option<u64>) -> Vec<ImageVersion> {
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+        if self.is_hidden_block(&root_id) {
+            return Vec::new();
+        }
+
+        self.image_history.get(&root_id)
+            .map(|history| history.versions)
+            .unwrap_or_default()
+            .into_iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .collect()
+    }
+
+    /// Image of the block rooted at `root_id`, `None` while the block is hidden by a moderator.
+    pub fn get_block_image(&self, root_id: TokenId) -> Option<PixelImage> {
+        if self.is_hidden_block(&root_id) {
+            return None;
+        }
+        self.internal_block_image(&root_id).map(|image| self.to_pixel_image(root_id, image))
+    }
+
+    /// Images of all blocks, paginated over the order they were first set in, followed by
+    /// rented blocks that only show a renter's image. Hidden blocks and rentals without an image
+    /// are left out before paging, so a page shorter than `limit` is the last one.
+    pub fn get_block_images(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<PixelImage> {
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+
+        self.block_images
+            .keys()
+            .chain(self.rentals.keys().filter(|root_id| self.block_images.get(root_id).is_none()))
+            .filter(|root_id| !self.is_hidden_block(root_id))
+            .filter_map(|root_id| self.internal_block_image(&root_id).map(|image| (root_id, image)))
+            .skip(start_index as usize)
+            .take(limit)
+            .map(|(root_id, image)| self.to_pixel_image(root_id, image))
+            .collect()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_set_block_image(&mut self, root_id: &TokenId, image: BlockImage, owner_id: &AccountId) {
+        let mut history = self.image_history.get(root_id).unwrap_or_default();
+        history.versions.push(ImageVersion {
+            version: history.next_version,
+            image: image.clone(),
+            set_by: owner_id.clone(),
+            set_at: U64(env::block_timestamp()),
+        });
+        history.next_version += 1;
+        if history.versions.len() > IMAGE_HISTORY_LEN {
+            history.versions.remove(0);
+        }
+        self.image_history.insert(root_id, &history);
+
+        self.block_images.insert(root_id, &image);
+        PixelImageSet { owner_id, root_id, cid: &image.cid, mime: &image.mime }.emit();
+    }
+
+    fn internal_set_rental_image(&mut self, root_id: &TokenId, image: BlockImage, renter_id: &AccountId) {
+        let mut rental = self.internal_active_rental(root_id).unwrap_or_else(|| env::panic_str("Block is not rented"));
+        PixelImageSet { owner_id: renter_id, root_id, cid: &image.cid, mime: &image.mime }.emit();
+        rental.image = Some(image);
+        self.internal_update_active_rental(root_id, rental);
+    }
+
+    fn to_pixel_image(&self, root_id: TokenId, image: BlockImage) -> PixelImage {
+        let (pixel_id, w, h) = shape_bounds(&self.get_token_shape(root_id.clone()).rects);
+        PixelImage {
+            pixel_id,
+            root_id,
+            cid: image.cid,
+            mime: image.mime,
+            hash: image.hash,
+            w,
+            h,
+        }
+    }
+}
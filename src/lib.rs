@@ -19,25 +19,92 @@ use near_contract_standards::non_fungible_token::events::NftMint;
 use near_contract_standards::non_fungible_token::metadata::{
     NFTContractMetadata, NonFungibleTokenMetadataProvider, TokenMetadata, NFT_METADATA_SPEC,
 };
+use near_contract_standards::non_fungible_token::core::{NonFungibleTokenCore, NonFungibleTokenResolver};
+use near_contract_standards::non_fungible_token::enumeration::NonFungibleTokenEnumeration;
 use near_contract_standards::non_fungible_token::{Token, TokenId};
-use near_contract_standards::non_fungible_token::NonFungibleToken;
-use near_sdk::serde::{Serialize};
+use near_contract_standards::non_fungible_token::{refund_deposit, NonFungibleToken};
+use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::LazyOption;
-use near_sdk::collections::LookupMap;
+use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet};
+use near_sdk::json_types::U128;
 use near_sdk::{
     env, near_bindgen, AccountId, Balance, BorshStorageKey, PanicOnDefault, Promise, PromiseOrValue,
 };
 
+use std::collections::{HashMap, HashSet};
+
+mod auction;
+mod block;
+mod dutch;
+mod embed;
+mod harberger;
+pub mod events;
+mod image;
+mod limits;
+mod link;
+mod market;
+mod offer;
+mod moderation;
+mod paint;
+mod rental;
+mod royalty;
+mod staking;
+mod token_view;
 mod utils;
+mod vault;
+use block::*;
+use market::DEFAULT_MARKET_FEE;
+use paint::DEFAULT_PAINT_COOLDOWN;
+use royalty::DEFAULT_MINTER_ROYALTY;
+use staking::{RewardAccumulator, DEFAULT_REWARD_RATE};
+use events::{PixelLinkClear, PixelMerge, PixelUnmerge, PriceUpdate};
+use image::ImageHistory;
+pub use auction::{Auction, Bid, AUCTION_EXTENSION};
+pub use dutch::DutchAuction;
+pub use embed::{EmbedAuthorization, EmbeddedNft};
+pub use harberger::{HarbergerAccount, TaxAccumulator, YEAR};
+pub use image::{BlockImage, ImageVersion, PixelImage};
+pub use limits::MergeLimits;
+pub use link::BlockLink;
+pub use market::BlockListing;
+pub use offer::Offer;
+pub use rental::{BlockContent, Rental, RentalTerms, DAY};
+pub use royalty::{Payout, RoyaltyConfig};
+pub use staking::{Stake, EPOCH};
+pub use vault::{ImageProposal, LockedShares, Vault, VAULT_PROPOSAL_DURATION};
+pub use moderation::{ModerationCase, Takedown};
 use utils::*;
 
+/// A rectangle of cells whose top-left cell is `token_id`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MergeRect {
+    pub token_id: TokenId,
+    pub width: u8,
+    pub height: u8,
+}
+
+/// Shape of a merged token, described as the set of rectangles it is made of.
+/// A token that is not merged is a single 1x1 rectangle.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenShape {
+    token_id: TokenId,
+    rects: Vec<MergeRect>,
+}
+
+/// Everything the map needs to draw and link a block.
 #[derive(Serialize, Debug, PartialEq)]
 #[serde(crate = "near_sdk::serde")]
-pub struct UncoveredToken {
+pub struct BlockView {
     token_id: TokenId,
-    width: u8,
-    height: u8
+    owner_id: AccountId,
+    rects: Vec<MergeRect>,
+    image: Option<BlockImage>,
+    link: Option<BlockLink>,
+    embedded_nft: Option<EmbeddedNft>,
+    hidden: bool,
 }
 
 #[near_bindgen]
@@ -46,8 +113,46 @@ pub struct Contract {
     tokens: NonFungibleToken,
     metadata: LazyOption<NFTContractMetadata>,
     mint_price: Balance,
-    token_merge: LookupMap<TokenId, (u8, u8)>,
+    token_merge: LookupMap<TokenId, Vec<MergeRect>>,
     token_merged: LookupMap<TokenId, TokenId>,
+    merge_limits: MergeLimits,
+    block_images: UnorderedMap<TokenId, BlockImage>,
+    image_history: LookupMap<TokenId, ImageHistory>,
+    block_links: LookupMap<TokenId, BlockLink>,
+    cell_colors: LookupMap<TokenId, [u8; 3]>,
+    paint_cooldown: u64,
+    last_public_paint: LookupMap<AccountId, u64>,
+    moderators: UnorderedSet<AccountId>,
+    moderation_queue: UnorderedMap<TokenId, ModerationCase>,
+    block_takedowns: LookupMap<TokenId, Takedown>,
+    last_flag_at: LookupMap<AccountId, u64>,
+    embedded_nfts: LookupMap<TokenId, EmbeddedNft>,
+    listings: UnorderedMap<TokenId, BlockListing>,
+    market_fee: u16,
+    auctions: UnorderedMap<TokenId, Auction>,
+    dutch_auctions: UnorderedMap<TokenId, DutchAuction>,
+    dutch_auction_cells: LookupMap<TokenId, TokenId>,
+    offers: UnorderedMap<u64, Offer>,
+    offers_by_token: LookupMap<TokenId, Vec<u64>>,
+    next_offer_id: u64,
+    royalty: RoyaltyConfig,
+    block_royalties: LookupMap<TokenId, RoyaltyConfig>,
+    minters: LookupMap<TokenId, AccountId>,
+    rental_terms: LookupMap<TokenId, RentalTerms>,
+    rentals: UnorderedMap<TokenId, Vec<Rental>>,
+    rental_income: LookupMap<AccountId, Balance>,
+    harberger_tax_rate: u16,
+    harberger_accounts: LookupMap<TokenId, HarbergerAccount>,
+    harberger_tax_collected: Balance,
+    vaults: LookupMap<TokenId, Vault>,
+    stakes: LookupMap<TokenId, Stake>,
+    reward_rate: Balance,
+    reward_accumulator: RewardAccumulator,
+    reward_points: LookupMap<AccountId, Balance>,
+    vault_token_code: LazyOption<Vec<u8>>,
+    embed_authorizations: LookupMap<TokenId, EmbedAuthorization>,
+    harberger_accumulator: TaxAccumulator,
+    next_vault_id: u64,
 }
 
 const DATA_IMAGE_SVG_NEAR_ICON: &str = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 288 288'%3E%3Cg id='l' data-name='l'%3E%3Cpath d='M187.58,79.81l-30.1,44.69a3.2,3.2,0,0,0,4.75,4.2L191.86,103a1.2,1.2,0,0,1,2,.91v80.46a1.2,1.2,0,0,1-2.12.77L102.18,77.93A15.35,15.35,0,0,0,90.47,72.5H87.34A15.34,15.34,0,0,0,72,87.84V201.16A15.34,15.34,0,0,0,87.34,216.5h0a15.35,15.35,0,0,0,13.08-7.31l30.1-44.69a3.2,3.2,0,0,0-4.75-4.2L96.14,186a1.2,1.2,0,0,1-2-.91V104.61a1.2,1.2,0,0,1,2.12-.77l89.55,107.23a15.35,15.35,0,0,0,11.71,5.43h3.13A15.34,15.34,0,0,0,216,201.16V87.84A15.34,15.34,0,0,0,200.66,72.5h0A15.35,15.35,0,0,0,187.58,79.81Z'/%3E%3C/g%3E%3C/svg%3E";
@@ -61,6 +166,34 @@ enum StorageKey {
     Approval,
     TokenMerge,
     TokenMerged,
+    BlockImages,
+    ImageHistory,
+    BlockLinks,
+    CellColors,
+    LastPublicPaint,
+    Moderators,
+    ModerationQueue,
+    BlockTakedowns,
+    LastFlagAt,
+    EmbeddedNfts,
+    Listings,
+    Auctions,
+    DutchAuctions,
+    DutchAuctionCells,
+    Offers,
+    OffersByToken,
+    BlockRoyalties,
+    Minters,
+    RentalTerms,
+    Rentals,
+    RentalIncome,
+    HarbergerAccounts,
+    Vaults,
+    VaultLocks { vault_hash: Vec<u8> },
+    Stakes,
+    RewardPoints,
+    VaultTokenCode,
+    EmbedAuthorizations,
 }
 
 #[near_bindgen]
@@ -77,7 +210,8 @@ impl Contract {
                 name: "Example NEAR non-fungible token".to_string(),
                 symbol: "EXAMPLE".to_string(),
                 icon: Some(DATA_IMAGE_SVG_NEAR_ICON.to_string()),
-                base_uri: None,
+                // block images are stored as IPFS CIDs in token `media`
+                base_uri: Some("https://ipfs.io/ipfs".to_string()),
                 reference: None,
                 reference_hash: None,
             },
@@ -91,7 +225,7 @@ impl Contract {
         Self {
             tokens: NonFungibleToken::new(
                 StorageKey::NonFungibleToken,
-                owner_id,
+                owner_id.clone(),
                 Some(StorageKey::TokenMetadata),
                 Some(StorageKey::Enumeration),
                 Some(StorageKey::Approval),
@@ -100,6 +234,44 @@ impl Contract {
             mint_price,
             token_merge: LookupMap::new(StorageKey::TokenMerge),
             token_merged: LookupMap::new(StorageKey::TokenMerged),
+            merge_limits: MergeLimits::default(),
+            block_images: UnorderedMap::new(StorageKey::BlockImages),
+            image_history: LookupMap::new(StorageKey::ImageHistory),
+            block_links: LookupMap::new(StorageKey::BlockLinks),
+            cell_colors: LookupMap::new(StorageKey::CellColors),
+            paint_cooldown: DEFAULT_PAINT_COOLDOWN,
+            last_public_paint: LookupMap::new(StorageKey::LastPublicPaint),
+            moderators: UnorderedSet::new(StorageKey::Moderators),
+            moderation_queue: UnorderedMap::new(StorageKey::ModerationQueue),
+            block_takedowns: LookupMap::new(StorageKey::BlockTakedowns),
+            last_flag_at: LookupMap::new(StorageKey::LastFlagAt),
+            embedded_nfts: LookupMap::new(StorageKey::EmbeddedNfts),
+            listings: UnorderedMap::new(StorageKey::Listings),
+            market_fee: DEFAULT_MARKET_FEE,
+            auctions: UnorderedMap::new(StorageKey::Auctions),
+            dutch_auctions: UnorderedMap::new(StorageKey::DutchAuctions),
+            dutch_auction_cells: LookupMap::new(StorageKey::DutchAuctionCells),
+            offers: UnorderedMap::new(StorageKey::Offers),
+            offers_by_token: LookupMap::new(StorageKey::OffersByToken),
+            next_offer_id: 0,
+            royalty: RoyaltyConfig { treasury_id: owner_id, minter_bps: DEFAULT_MINTER_ROYALTY, treasury_bps: 0 },
+            block_royalties: LookupMap::new(StorageKey::BlockRoyalties),
+            minters: LookupMap::new(StorageKey::Minters),
+            rental_terms: LookupMap::new(StorageKey::RentalTerms),
+            rentals: UnorderedMap::new(StorageKey::Rentals),
+            rental_income: LookupMap::new(StorageKey::RentalIncome),
+            harberger_tax_rate: 0,
+            harberger_accounts: LookupMap::new(StorageKey::HarbergerAccounts),
+            harberger_tax_collected: 0,
+            vaults: LookupMap::new(StorageKey::Vaults),
+            stakes: LookupMap::new(StorageKey::Stakes),
+            reward_rate: DEFAULT_REWARD_RATE,
+            reward_accumulator: RewardAccumulator { acc_reward_per_weight: 0, updated_at: env::block_timestamp() },
+            reward_points: LookupMap::new(StorageKey::RewardPoints),
+            vault_token_code: LazyOption::new(StorageKey::VaultTokenCode, None),
+            embed_authorizations: LookupMap::new(StorageKey::EmbedAuthorizations),
+            harberger_accumulator: TaxAccumulator { acc_tax_per_valuation: 0, updated_at: env::block_timestamp() },
+            next_vault_id: 0,
         }
     }
 
@@ -119,7 +291,25 @@ impl Contract {
         token_metadata: TokenMetadata,
     ) -> Token {
         assert!(env::attached_deposit() >= self.mint_price, "Not enough mint pay");
-        self.tokens.internal_mint(token_id, receiver_id, Some(token_metadata))
+        assert!(!is_block_token(&token_id), "Invalid token id");
+        assert!(is_cell_id(&token_id), "Invalid cell id");
+        self.assert_not_reserved(&token_id);
+        let mut token = self.tokens.internal_mint(token_id, receiver_id, Some(token_metadata));
+        if let Some(owner_id) = self.internal_on_mint(&token.token_id, &token.owner_id) {
+            token.owner_id = owner_id;
+        }
+        token
+    }
+
+    /// Owner only, change the price of minting one cell.
+    pub fn set_mint_price(&mut self, mint_price: U128) {
+        assert_eq!(env::predecessor_account_id(), self.tokens.owner_id, "Unauthorized");
+        PriceUpdate { old_price: U128(self.mint_price), new_price: mint_price }.emit();
+        self.mint_price = mint_price.0;
+    }
+
+    pub fn get_mint_price(&self) -> U128 {
+        U128(self.mint_price)
     }
 
     #[payable]
@@ -133,6 +323,7 @@ impl Contract {
         iterate_token_area(token_id, width, height, |sub_token_id| -> bool {
             // mint token without refund (refund_id is None)
             // TODO all token using same metadata
+            self.assert_not_reserved(&sub_token_id);
             let token = self.tokens.internal_mint_with_refund(sub_token_id.clone(), receiver_id.clone(), Some(token_metadata.clone()), None);
             tokens.push(token);
             token_ids_vec.push(sub_token_id);
@@ -142,47 +333,186 @@ impl Contract {
         // emit event
         let token_ids: Vec<&str> = token_ids_vec.iter().map(|id| id.as_str()).collect();
         NftMint { owner_id: &receiver_id, token_ids: &token_ids[..], memo: None }.emit();
+        for token in &mut tokens {
+            if let Some(owner_id) = self.internal_on_mint(&token.token_id, &token.owner_id) {
+                token.owner_id = owner_id;
+            }
+        }
 
         // TODO refund
 
         tokens
     }
 
-    pub fn nft_merge(&mut self, token_id: TokenId, width: u8, height: u8) {
+    /// Merge the cells covered by `rects` into one token rooted at the first rectangle's
+    /// top-left cell. The rectangles must not overlap, must form a 4-connected shape and every
+    /// covered cell must be owned by the caller and not be part of another merge.
+    ///
+    /// The attached deposit must cover the storage used by the merge, the excess is refunded.
+    #[payable]
+    pub fn nft_merge(&mut self, rects: Vec<MergeRect>) {
+        assert!(!rects.is_empty(), "Empty merge shape");
+        assert!(rects.iter().all(|rect| rect.width > 0 && rect.height > 0), "Invalid merge rectangle");
+        self.merge_limits.assert_shape_allowed(&rects);
+        let initial_storage_usage = env::storage_usage();
+        let token_id = rects[0].token_id.clone();
         let owner_id = expect_token_found(self.tokens.owner_by_id.get(&token_id));
         assert_eq!(owner_id, env::predecessor_account_id(), "No permission to merge");
 
         let mut sub_token_ids: Vec<TokenId> = Vec::new();
+        let mut cells: HashSet<(i128, i128)> = HashSet::new();
+
+        for rect in &rects {
+            iterate_token_area(rect.token_id.clone(), rect.width, rect.height, |sub_token_id| -> bool {
+                assert!(cells.insert(get_coord(sub_token_id.clone())), "Overlapping merge rectangles");
+                assert!(
+                    !self.is_covered_token(&sub_token_id) && self.token_merge.get(&sub_token_id).is_none(),
+                    "Token already merged"
+                );
+                self.assert_not_on_auction(&sub_token_id);
+                self.assert_not_staked(&sub_token_id);
+                assert!(!self.is_booked(&sub_token_id), "Block is rented");
+                if sub_token_id != token_id {
+                    let sub_owner_id = expect_token_found(self.tokens.owner_by_id.get(&sub_token_id));
+                    assert_eq!(sub_owner_id, owner_id, "No permission to merge");
+                    sub_token_ids.push(sub_token_id);
+                }
+                true
+            });
+        }
 
-        iterate_token_area(token_id.clone(), width, height, |sub_token_id| -> bool {
-            if sub_token_id != token_id {
-                let sub_owner_id = expect_token_found(self.tokens.owner_by_id.get(&sub_token_id));
-                assert_eq!(sub_owner_id, owner_id, "No permission to merge");
-                sub_token_ids.push(sub_token_id);
-            }
-            true
-        });
+        assert!(is_connected(&cells), "Merge shape is not connected");
 
-        self.token_merge.insert(&token_id, &(width, height));
+        self.token_merge.insert(&token_id, &rects);
         for sub_token_id in &sub_token_ids {
-            self.token_merged.insert(&sub_token_id, &token_id);
+            self.token_merged.insert(sub_token_id, &token_id);
+            self.internal_remove_listing(sub_token_id);
+            self.rental_terms.remove(sub_token_id);
+            self.internal_settle_bookings(sub_token_id);
+        }
+        self.internal_remove_listing(&token_id);
+        let mut cells = sub_token_ids.clone();
+        cells.push(token_id.clone());
+        self.internal_merge_harberger(&token_id, &cells);
+        PixelMerge { owner_id: &owner_id, root_id: &token_id, rects: &rects }.emit();
+
+        self.internal_settle_storage(initial_storage_usage);
+    }
+
+    /// Split the block rooted at `root_id` back into single cells,
+    /// refunding the released storage to the caller. Splitting a Harberger account over the cells
+    /// takes more storage than it releases, the attached deposit must then cover it.
+    #[payable]
+    pub fn nft_unmerge(&mut self, root_id: TokenId) {
+        assert!(env::attached_deposit() >= 1, "Requires attached deposit of at least 1 yoctoNEAR");
+        self.assert_not_on_auction(&root_id);
+        self.assert_not_staked(&root_id);
+        assert!(!self.is_booked(&root_id), "Block is rented");
+        let initial_storage_usage = env::storage_usage();
+        if self.is_locked_block(root_id.clone()) {
+            self.internal_unlock_block(&root_id, &env::predecessor_account_id());
         }
+        let owner_id = expect_token_found(self.tokens.owner_by_id.get(&root_id));
+        assert_eq!(owner_id, env::predecessor_account_id(), "No permission to unmerge");
+
+        let cells = self.get_block_cells(root_id.clone());
+        let rects = self.token_merge.remove(&root_id).unwrap_or_else(|| env::panic_str("Token is not merged"));
+        for rect in rects {
+            iterate_token_area(rect.token_id, rect.width, rect.height, |sub_token_id| -> bool {
+                self.token_merged.remove(&sub_token_id);
+                true
+            });
+        }
+        self.block_images.remove(&root_id);
+        self.block_links.remove(&root_id);
+        self.internal_remove_listing(&root_id);
+        self.rental_terms.remove(&root_id);
+        self.internal_settle_bookings(&root_id);
+        self.internal_split_harberger(&root_id, &cells);
+        PixelUnmerge { owner_id: &owner_id, root_id: &root_id }.emit();
+
+        self.internal_settle_storage(initial_storage_usage);
     }
 
-    pub fn get_token_with_size(&self, token_id: TokenId) -> UncoveredToken {
-        let (width, height) = self.token_merge.get(&token_id).unwrap_or((1, 1));
-        UncoveredToken {
+    pub fn get_token_shape(&self, token_id: TokenId) -> TokenShape {
+        let rects = self.token_merge.get(&token_id).unwrap_or_else(|| {
+            vec![MergeRect { token_id: token_id.clone(), width: 1, height: 1 }]
+        });
+        TokenShape {
             token_id,
-            width,
-            height
+            rects
         }
     }
 
-    pub fn get_not_covered_tokens(&self) -> Vec<UncoveredToken> {
+    pub fn get_not_covered_tokens(&self) -> Vec<TokenShape> {
         self.tokens.owner_by_id
             .iter()
-            .filter(|(token_id, _)| !self.is_covered_token(token_id))
-            .map(|(token_id, _)| self.get_token_with_size(token_id))
+            .filter(|(token_id, _)| !is_block_token(token_id) && !self.is_covered_token(token_id))
+            .map(|(token_id, _)| self.get_token_shape(token_id))
+            .collect()
+    }
+
+    /// Shape, owner and content of the block rooted at `root_id`.
+    /// The content of a block hidden by a moderator is left out.
+    pub fn get_block(&self, root_id: TokenId) -> BlockView {
+        let owner_id = self.internal_block_owner(&root_id);
+        let hidden = self.is_hidden_block(&root_id);
+        BlockView {
+            owner_id,
+            rects: self.get_token_shape(root_id.clone()).rects,
+            image: if hidden { None } else { self.internal_block_image(&root_id) },
+            link: if hidden { None } else { self.internal_block_link(&root_id) },
+            embedded_nft: if hidden { None } else { self.embedded_nfts.get(&root_id) },
+            hidden,
+            token_id: root_id,
+        }
+    }
+
+    /// Root of the block `token_id` belongs to, the token itself if it is not covered by a merge.
+    pub fn get_merge_root(&self, token_id: TokenId) -> Option<TokenId> {
+        self.tokens.owner_by_id.get(&token_id)?;
+        Some(self.token_merged.get(&token_id).unwrap_or(token_id))
+    }
+
+    /// All cells of the block rooted at `root_id`, the root included.
+    pub fn get_block_cells(&self, root_id: TokenId) -> Vec<TokenId> {
+        assert!(is_cell_id(&root_id), "Invalid cell id");
+        assert!(!self.is_covered_token(&root_id), "Token is not a block root");
+        expect_token_found(self.tokens.owner_by_id.get(&root_id));
+
+        let mut cells: Vec<TokenId> = Vec::new();
+        for rect in self.get_token_shape(root_id).rects {
+            iterate_token_area(rect.token_id, rect.width, rect.height, |sub_token_id| -> bool {
+                cells.push(sub_token_id);
+                true
+            });
+        }
+        cells
+    }
+
+    /// Blocks (merged or single cells) owned by `account_id`, paginated like `nft_tokens_for_owner`.
+    pub fn get_blocks_for_owner(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<TokenShape> {
+        let tokens_per_owner = self.tokens.tokens_per_owner.as_ref()
+            .unwrap_or_else(|| env::panic_str("Could not find tokens_per_owner when calling a method on the enumeration standard."));
+        let token_set = match tokens_per_owner.get(&account_id) {
+            Some(token_set) => token_set,
+            None => return vec![]
+        };
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+
+        token_set
+            .iter()
+            .filter(|token_id| !self.is_covered_token(token_id))
+            .skip(start_index as usize)
+            .take(limit)
+            .map(|token_id| self.get_token_shape(block_root_id(&token_id).unwrap_or(token_id)))
             .collect()
     }
 
@@ -194,9 +524,120 @@ impl Contract {
     }
 }
 
-near_contract_standards::impl_non_fungible_token_core!(Contract, tokens);
+impl Contract {
+    /// Charge the caller for the storage used since `initial_storage_usage` out of the attached
+    /// deposit, or refund the attached deposit together with the storage released.
+    pub(crate) fn internal_settle_storage(&self, initial_storage_usage: u64) {
+        let storage_usage = env::storage_usage();
+        if storage_usage >= initial_storage_usage {
+            refund_deposit(storage_usage - initial_storage_usage);
+        } else {
+            let storage_released = initial_storage_usage - storage_usage;
+            Promise::new(env::predecessor_account_id())
+                .transfer(env::attached_deposit() + Balance::from(storage_released) * env::storage_byte_cost());
+        }
+    }
+
+    /// Called after `token_id` changed hands, clears the per-owner content, listing, rental terms,
+    /// embed authorization and Harberger account of the block it belongs to and sends an embedded token and the tax deposit
+    /// back to the previous owner. Bookings stay with the block.
+    pub(crate) fn internal_on_transfer(&mut self, token_id: &TokenId) {
+        let root_id = match block_root_id(token_id) {
+            Some(root_id) => root_id,
+            None => self.token_merged.get(token_id).unwrap_or_else(|| token_id.clone()),
+        };
+        self.assert_not_on_auction(&root_id);
+        self.assert_not_staked(&root_id);
+        if self.block_links.remove(&root_id).is_some() {
+            PixelLinkClear { root_id: &root_id }.emit();
+        }
+        self.internal_remove_listing(&root_id);
+        self.rental_terms.remove(&root_id);
+        self.embed_authorizations.remove(&root_id);
+        self.internal_close_harberger(&root_id);
+        if let Some(embedded_nft) = self.embedded_nfts.get(&root_id) {
+            if embedded_nft.owner_id != self.internal_block_owner(&root_id) {
+                self.internal_release_embedded_nft(&root_id);
+            }
+        }
+    }
+}
+
+#[near_bindgen]
+impl NonFungibleTokenCore for Contract {
+    #[payable]
+    fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) {
+        self.tokens.nft_transfer(receiver_id, token_id.clone(), approval_id, memo);
+        self.internal_on_transfer(&token_id);
+    }
+
+    #[payable]
+    fn nft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        let result = self.tokens.nft_transfer_call(receiver_id, token_id.clone(), approval_id, memo, msg);
+        self.internal_on_transfer(&token_id);
+        result
+    }
+
+    fn nft_token(&self, token_id: TokenId) -> Option<Token> {
+        self.tokens.nft_token(token_id).map(|token| self.internal_token_view(token))
+    }
+}
+
+#[near_bindgen]
+impl NonFungibleTokenResolver for Contract {
+    #[private]
+    fn nft_resolve_transfer(
+        &mut self,
+        previous_owner_id: AccountId,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approved_account_ids: Option<HashMap<AccountId, u64>>,
+    ) -> bool {
+        self.tokens.nft_resolve_transfer(previous_owner_id, receiver_id, token_id, approved_account_ids)
+    }
+}
+
 near_contract_standards::impl_non_fungible_token_approval!(Contract, tokens);
-near_contract_standards::impl_non_fungible_token_enumeration!(Contract, tokens);
+
+#[near_bindgen]
+impl NonFungibleTokenEnumeration for Contract {
+    fn nft_total_supply(&self) -> U128 {
+        self.tokens.nft_total_supply()
+    }
+
+    fn nft_tokens(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
+        self.tokens
+            .nft_tokens(from_index, limit)
+            .into_iter()
+            .map(|token| self.internal_token_view(token))
+            .collect()
+    }
+
+    fn nft_supply_for_owner(&self, account_id: AccountId) -> U128 {
+        self.tokens.nft_supply_for_owner(account_id)
+    }
+
+    fn nft_tokens_for_owner(&self, account_id: AccountId, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
+        self.tokens
+            .nft_tokens_for_owner(account_id, from_index, limit)
+            .into_iter()
+            .map(|token| self.internal_token_view(token))
+            .collect()
+    }
+}
 
 #[near_bindgen]
 impl NonFungibleTokenMetadataProvider for Contract {
@@ -207,8 +648,10 @@ impl NonFungibleTokenMetadataProvider for Contract {
 
 #[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests {
-    use std::collections::HashMap;
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+    use near_contract_standards::non_fungible_token::core::NonFungibleTokenReceiver;
+    use near_sdk::json_types::{Base64VecU8, U64};
+    use near_sdk::test_utils::{self, accounts, VMContextBuilder};
     use near_sdk::testing_env;
 
     use super::*;
@@ -252,6 +695,14 @@ mod tests {
         contract.nft_batch_mint(token_id.clone(), width, height, account, sample_token_metadata())
     }
 
+    fn sample_hash() -> Base64VecU8 {
+        Base64VecU8(env::sha256(b"pixel"))
+    }
+
+    fn rect(token_id: &str, width: u8, height: u8) -> MergeRect {
+        MergeRect { token_id: token_id.to_string(), width, height }
+    }
+
     #[test]
     fn test_batch_mint() {
         let mut context = get_context(accounts(0));
@@ -272,12 +723,12 @@ mod tests {
         prepare_mint_token(&mut context, &mut contract, accounts(1), "19".to_string(), 2, 3);
         prepare_mint_token(&mut context, &mut contract, accounts(0), "7".to_string(), 3, 2);
 
-        contract.nft_merge("7".to_string(), 3, 2);
+        contract.nft_merge(vec![rect("7", 3, 2)]);
 
         let sub_token_ids = vec!["0", "8", "1", "9", "10"];
 
-        let dimension = contract.token_merge.get(&"7".to_string());
-        assert_eq!(dimension, Some((3, 2)));
+        let shape = contract.token_merge.get(&"7".to_string());
+        assert_eq!(shape, Some(vec![rect("7", 3, 2)]));
 
         for sub_token_id in sub_token_ids {
             let sub_token_id: TokenId = sub_token_id.to_string();
@@ -286,6 +737,1544 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_nft_merge_l_shape() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+
+        // 7 8 9
+        // 0 1 10 (area minted from 7, 3x2)
+        prepare_mint_token(&mut context, &mut contract, accounts(0), "7".to_string(), 3, 2);
+
+        // L shape: left column 7,0 and top row 8,9
+        contract.nft_merge(vec![rect("7", 1, 2), rect("8", 2, 1)]);
+
+        let shape = contract.get_token_shape("7".to_string());
+        assert_eq!(shape.rects, vec![rect("7", 1, 2), rect("8", 2, 1)]);
+
+        for sub_token_id in ["0", "8", "9"] {
+            assert_eq!(contract.token_merged.get(&sub_token_id.to_string()), Some("7".to_string()));
+        }
+        assert_eq!(contract.token_merged.get(&"1".to_string()), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Merge shape is not connected")]
+    fn test_nft_merge_not_connected() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(0), "7".to_string(), 3, 2);
+
+        // 7 and 1 only touch diagonally
+        contract.nft_merge(vec![rect("7", 1, 1), rect("1", 1, 1)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Overlapping merge rectangles")]
+    fn test_nft_merge_overlapping() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(0), "7".to_string(), 3, 2);
+
+        contract.nft_merge(vec![rect("7", 2, 2), rect("8", 2, 1)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "to cover storage")]
+    fn test_nft_merge_requires_storage_deposit() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(0), "7".to_string(), 3, 2);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .build());
+        contract.nft_merge(vec![rect("7", 3, 2)]);
+    }
+
+    #[test]
+    fn test_nft_unmerge() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(0), "7".to_string(), 3, 2);
+
+        let storage_before_merge = env::storage_usage();
+        contract.nft_merge(vec![rect("7", 3, 2)]);
+        assert!(env::storage_usage() > storage_before_merge);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .build());
+        contract.nft_unmerge("7".to_string());
+
+        assert_eq!(env::storage_usage(), storage_before_merge);
+        assert_eq!(contract.get_block_cells("7".to_string()), vec!["7"]);
+        assert_eq!(contract.get_merge_root("10".to_string()), Some("10".to_string()));
+    }
+
+    #[test]
+    fn test_lock_block() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 3, 2);
+        contract.nft_merge(vec![rect("7", 3, 2)]);
+
+        let token = contract.nft_lock_block("7".to_string());
+        assert_eq!(token.token_id, "block:7");
+        assert_eq!(token.owner_id, accounts(1));
+        let extra = token.metadata.unwrap().extra.unwrap();
+        assert!(extra.contains("\"width\":3") && extra.contains("\"height\":2"));
+
+        assert_eq!(contract.nft_supply_for_owner(accounts(1)).0, 1);
+        for cell in contract.get_block_cells("7".to_string()) {
+            assert_eq!(contract.nft_token(cell).unwrap().owner_id, accounts(0));
+        }
+        let blocks = contract.get_blocks_for_owner(accounts(1), None, None);
+        assert_eq!(blocks, vec![TokenShape { token_id: "7".to_string(), rects: vec![rect("7", 3, 2)] }]);
+
+        // the block token is tradable, the new owner gets the cells back on unmerge
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .build());
+        contract.nft_transfer(accounts(2), "block:7".to_string(), None, None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.nft_unmerge("7".to_string());
+
+        assert!(contract.nft_token("block:7".to_string()).is_none());
+        assert!(!contract.is_locked_block("7".to_string()));
+        assert_eq!(contract.nft_supply_for_owner(accounts(2)).0, 6);
+        assert_eq!(contract.get_merge_root("10".to_string()), Some("10".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Merge aspect ratio exceeds 2:1")]
+    fn test_nft_merge_limits() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        contract.set_merge_limits(MergeLimits { max_area: 4, max_width: 3, max_height: 3, max_aspect_ratio: 2 });
+        assert_eq!(contract.get_merge_limits().max_area, 4);
+
+        prepare_mint_token(&mut context, &mut contract, accounts(0), "7".to_string(), 3, 2);
+        contract.nft_merge(vec![rect("7", 2, 2)]);
+        contract.nft_merge(vec![rect("9", 1, 1), rect("10", 1, 1)]);
+        // 3x1 sliver
+        contract.nft_merge(vec![rect("7", 3, 1)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Merge area exceeds 4")]
+    fn test_nft_merge_max_area() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        contract.set_merge_limits(MergeLimits { max_area: 4, max_width: 3, max_height: 3, max_aspect_ratio: 2 });
+
+        prepare_mint_token(&mut context, &mut contract, accounts(0), "7".to_string(), 3, 2);
+        contract.nft_merge(vec![rect("7", 3, 2)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unauthorized")]
+    fn test_set_merge_limits_owner_only() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        contract.set_merge_limits(MergeLimits::default());
+    }
+
+    #[test]
+    fn test_merge_events() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(0), "7".to_string(), 2, 1);
+
+        contract.nft_merge(vec![rect("7", 2, 1)]);
+        assert_eq!(
+            test_utils::get_logs().last().unwrap(),
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"pixel_merge","data":[{"owner_id":"alice","root_id":"7","rects":[{"token_id":"7","width":2,"height":1}]}]}"#
+        );
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .build());
+        contract.nft_unmerge("7".to_string());
+        assert_eq!(
+            test_utils::get_logs().last().unwrap(),
+            r#"EVENT_JSON:{"standard":"pixelland","version":"1.0.0","event":"pixel_unmerge","data":[{"owner_id":"alice","root_id":"7"}]}"#
+        );
+
+        contract.set_mint_price(U128(5));
+        assert_eq!(contract.get_mint_price(), U128(5));
+        assert_eq!(
+            test_utils::get_logs().last().unwrap(),
+            &format!(r#"EVENT_JSON:{{"standard":"pixelland","version":"1.0.0","event":"price_update","data":[{{"old_price":"{}","new_price":"5"}}]}}"#, ONE_NEAR_AMOUNT)
+        );
+    }
+
+    #[test]
+    fn test_block_image_bounding_box() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+
+        // 7 8 9
+        // 0 1 10, the block is rooted at 0 and covers 0, 1 and 8
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 3, 2);
+        contract.nft_merge(vec![rect("0", 2, 1), rect("8", 1, 1)]);
+        contract.set_block_image("0".to_string(), "bafy0".to_string(), "image/png".to_string(), sample_hash());
+
+        let image = contract.get_block_image("0".to_string()).unwrap();
+        assert_eq!((image.pixel_id.as_str(), image.root_id.as_str()), ("7", "0"));
+        assert_eq!((image.w, image.h), (2, 2));
+    }
+
+    #[test]
+    fn test_set_block_image() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "20".to_string(), 1, 1);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 3, 2);
+        contract.nft_merge(vec![rect("7", 3, 2)]);
+
+        contract.set_block_image("7".to_string(), "bafy7".to_string(), "image/png".to_string(), sample_hash());
+        contract.set_block_image("7".to_string(), "bafy7b".to_string(), "image/png".to_string(), sample_hash());
+        contract.set_block_image("20".to_string(), "bafy20".to_string(), "image/jpeg".to_string(), sample_hash());
+
+        let images = contract.get_block_images(None, None);
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0], PixelImage {
+            pixel_id: "7".to_string(),
+            root_id: "7".to_string(),
+            cid: "bafy7b".to_string(),
+            mime: "image/png".to_string(),
+            hash: sample_hash(),
+            w: 3,
+            h: 2,
+        });
+        assert_eq!(contract.get_block_images(Some(U128(1)), Some(1))[0].pixel_id, "20");
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .build());
+        contract.nft_unmerge("7".to_string());
+        assert_eq!(contract.get_block_image("7".to_string()), None);
+    }
+
+    #[test]
+    fn test_block_image_history() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+
+        for i in 0..12 {
+            contract.set_block_image("7".to_string(), format!("bafy{}", i), "image/png".to_string(), sample_hash());
+        }
+
+        let history = contract.get_block_image_history("7".to_string(), None, None);
+        assert_eq!(history.len(), image::IMAGE_HISTORY_LEN);
+        assert_eq!(history[0].version, 2);
+        assert_eq!(history[0].set_by, accounts(1));
+        assert_eq!(contract.get_block_image_history("7".to_string(), Some(U128(9)), Some(5))[0].image.cid, "bafy11");
+
+        contract.restore_block_image("7".to_string(), 3);
+        assert_eq!(contract.get_block_image("7".to_string()).unwrap().cid, "bafy3");
+        let history = contract.get_block_image_history("7".to_string(), None, None);
+        assert_eq!(history.last().unwrap().version, 12);
+        assert_eq!(history.last().unwrap().image.cid, "bafy3");
+    }
+
+    #[test]
+    fn test_verify_block_media() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        contract.set_block_image("7".to_string(), "bafy7".to_string(), "image/png".to_string(), sample_hash());
+
+        assert!(contract.verify_block_media("7".to_string(), Base64VecU8(b"pixel".to_vec())));
+        assert!(!contract.verify_block_media("7".to_string(), Base64VecU8(b"tampered".to_vec())));
+        assert!(!contract.verify_block_media("8".to_string(), Base64VecU8(b"pixel".to_vec())));
+    }
+
+    #[test]
+    #[should_panic(expected = "Hash must be a sha256 digest")]
+    fn test_set_block_image_requires_sha256() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        contract.set_block_image("7".to_string(), "bafy7".to_string(), "image/png".to_string(), Base64VecU8(vec![1, 2, 3]));
+    }
+
+    #[test]
+    #[should_panic(expected = "No permission to set image")]
+    fn test_set_block_image_owner_only() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.set_block_image("7".to_string(), "bafy7".to_string(), "image/png".to_string(), sample_hash());
+    }
+
+    #[test]
+    fn test_set_block_link() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 2, 1);
+        contract.nft_merge(vec![rect("7", 2, 1)]);
+
+        contract.set_block_link("7".to_string(), "https://near.org".to_string(), "NEAR".to_string());
+        let block = contract.get_block("7".to_string());
+        assert_eq!(block.owner_id, accounts(1));
+        assert_eq!(block.link, Some(BlockLink { url: "https://near.org".to_string(), caption: "NEAR".to_string() }));
+
+        // moving the block root to another owner clears the link
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .build());
+        contract.nft_transfer(accounts(2), "7".to_string(), None, None);
+        assert_eq!(contract.get_block_link("7".to_string()), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Url must use https")]
+    fn test_set_block_link_https_only() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+
+        contract.set_block_link("7".to_string(), "http://near.org".to_string(), "NEAR".to_string());
+    }
+
+    #[test]
+    fn test_paint_cells() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 2, 1);
+        contract.nft_merge(vec![rect("7", 2, 1)]);
+        contract.nft_lock_block("7".to_string());
+
+        // locked cells are painted by the block token owner
+        contract.paint_cells(vec![("7".to_string(), 0xFF0000), ("8".to_string(), 0x0000FF)]);
+        assert_eq!(contract.get_cell_color("8".to_string()), Some(0x0000FF));
+
+        let colors: Vec<u8> = contract.get_region_colors("7".to_string(), 3, 1).into();
+        assert_eq!(colors, vec![0xFF, 0, 0, 0, 0, 0xFF, 0, 0, 0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "No permission to paint")]
+    fn test_paint_cells_owner_only() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        prepare_mint_token(&mut context, &mut contract, accounts(2), "8".to_string(), 1, 1);
+
+        contract.paint_cells(vec![("7".to_string(), 0xFF0000)]);
+    }
+
+    #[test]
+    fn test_paint_public_canvas() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        contract.set_paint_cooldown(U64(100));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(1_000)
+            .build());
+        contract.paint_cells(vec![("3".to_string(), 0x123456)]);
+        assert_eq!(contract.get_cell_color("3".to_string()), Some(0x123456));
+        assert_eq!(contract.get_paint_cooldown(accounts(1)), U64(100));
+        assert_eq!(contract.get_paint_cooldown(accounts(2)), U64(0));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .block_timestamp(1_100)
+            .build());
+        assert_eq!(contract.get_paint_cooldown(accounts(1)), U64(0));
+        contract.paint_cells(vec![("4".to_string(), 0x654321)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Paint cooldown not over")]
+    fn test_paint_public_canvas_cooldown() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.paint_cells(vec![("3".to_string(), 0x123456)]);
+        contract.paint_cells(vec![("4".to_string(), 0x123456)]);
+    }
+
+    #[test]
+    fn test_moderation() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        contract.add_moderator(accounts(3));
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        contract.set_block_image("7".to_string(), "bafy7".to_string(), "image/png".to_string(), sample_hash());
+        contract.set_block_link("7".to_string(), "https://near.org".to_string(), "NEAR".to_string());
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "20".to_string(), 1, 1);
+        contract.set_block_image("20".to_string(), "bafy20".to_string(), "image/png".to_string(), sample_hash());
+
+        // bob flags
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(moderation::FLAG_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.flag_block("7".to_string(), 1);
+        let queue = contract.get_moderation_queue(None, None);
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue[0].case.flags[0].flagger_id, accounts(2));
+
+        // moderator hides
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.hide_block("7".to_string(), 1);
+        assert!(contract.get_moderation_queue(None, None).is_empty());
+        let block = contract.get_block("7".to_string());
+        assert!(block.hidden && block.image.is_none() && block.link.is_none());
+        assert!(contract.get_block_image_history("7".to_string(), None, None).is_empty());
+        // the hidden block does not take a slot of the page
+        let images = contract.get_block_images(None, Some(1));
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].root_id, "20");
+
+        // owner appeals, moderator restores
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.appeal_block("7".to_string(), "it is my logo".to_string());
+        assert_eq!(contract.get_moderation_queue(None, None)[0].case.appeal, Some("it is my logo".to_string()));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.restore_block("7".to_string());
+        assert!(contract.get_moderation_queue(None, None).is_empty());
+        assert_eq!(contract.get_block_image("7".to_string()).unwrap().cid, "bafy7");
+        assert_eq!(contract.get_block_image_history("7".to_string(), None, None).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Flagging too often")]
+    fn test_flag_block_rate_limit() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "8".to_string(), 1, 1);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(moderation::FLAG_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.flag_block("7".to_string(), 1);
+        contract.flag_block("8".to_string(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only moderators can moderate")]
+    fn test_hide_block_moderator_only() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+
+        contract.hide_block("7".to_string(), 1);
+    }
+
+    #[test]
+    fn test_nft_token_view() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 3, 2);
+        contract.nft_merge(vec![rect("7", 3, 2)]);
+        contract.set_block_image("7".to_string(), "bafy7".to_string(), "image/png".to_string(), sample_hash());
+
+        let root = contract.nft_token("7".to_string()).unwrap().metadata.unwrap();
+        assert_eq!(root.title, Some("Block (-1, -2) 3x2".to_string()));
+        assert_eq!(root.media, Some("bafy7".to_string()));
+        assert_eq!(root.media_hash, Some(sample_hash()));
+
+        let cell = contract.nft_token("10".to_string()).unwrap().metadata.unwrap();
+        assert_eq!(cell.title, Some("Pixel (1, -1)".to_string()));
+        assert_eq!(cell.media, Some("bafy7".to_string()));
+        let extra: near_sdk::serde_json::Value = near_sdk::serde_json::from_str(&cell.extra.unwrap()).unwrap();
+        assert_eq!(extra["root"], "7");
+        assert_eq!(extra["width"], 3);
+        assert_eq!(extra["height"], 2);
+        assert_eq!(extra["cid"], "bafy7");
+
+        let tokens = contract.nft_tokens_for_owner(accounts(1), None, Some(6));
+        assert!(tokens.iter().all(|token| token.metadata.as_ref().unwrap().media == Some("bafy7".to_string())));
+        assert_eq!(contract.nft_tokens(None, Some(1))[0].metadata.as_ref().unwrap().media, Some("bafy7".to_string()));
+    }
+
+    #[test]
+    fn test_embed_nft() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 2, 1);
+        contract.nft_merge(vec![rect("7", 2, 1)]);
+
+        // a token of another collection is only taken once bob authorized it
+        testing_env!(context.predecessor_account_id(accounts(4)).attached_deposit(0).build());
+        let msg = r#"{"root_id":"7"}"#.to_string();
+        assert!(matches!(contract.nft_on_transfer(accounts(1), accounts(1), "42".to_string(), msg.clone()), PromiseOrValue::Value(true)));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(ONE_NEAR_AMOUNT)
+            .build());
+        contract.authorize_embed("7".to_string(), accounts(4), "42".to_string());
+
+        // a token owned by bob is sent with nft_transfer_call, from another contract it is refused
+        testing_env!(context.predecessor_account_id(accounts(3)).attached_deposit(0).build());
+        assert!(matches!(contract.nft_on_transfer(accounts(1), accounts(1), "42".to_string(), msg.clone()), PromiseOrValue::Value(true)));
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        let returned = contract.nft_on_transfer(accounts(1), accounts(1), "42".to_string(), msg.clone());
+        assert!(matches!(returned, PromiseOrValue::Value(false)));
+        let embedded_nft = EmbeddedNft { nft_contract_id: accounts(4), token_id: "42".to_string(), owner_id: accounts(1) };
+        assert_eq!(contract.get_block("7".to_string()).embedded_nft, Some(embedded_nft));
+        assert_eq!(contract.get_embed_authorization("7".to_string()), None);
+
+        // the block already shows a token, and charlie does not own it
+        assert!(matches!(contract.nft_on_transfer(accounts(1), accounts(1), "43".to_string(), msg.clone()), PromiseOrValue::Value(true)));
+        assert!(matches!(contract.nft_on_transfer(accounts(2), accounts(2), "44".to_string(), msg), PromiseOrValue::Value(true)));
+
+        // the block changes hands, the token goes back to bob
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(1)
+            .build());
+        contract.nft_transfer(accounts(2), "7".to_string(), None, None);
+        assert_eq!(contract.get_embedded_nft("7".to_string()), None);
+        assert!(test_utils::get_logs().last().unwrap().contains("nft_embed_release"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid cell id")]
+    fn test_list_block_token_id() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 3, 2);
+        contract.nft_merge(vec![rect("7", 3, 2)]);
+        contract.nft_lock_block("7".to_string());
+        contract.list_block("block:7".to_string(), U128(ONE_NEAR_AMOUNT));
+    }
+
+    #[test]
+    fn test_market_buy() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "20".to_string(), 1, 1);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 3, 2);
+        contract.nft_merge(vec![rect("7", 3, 2)]);
+
+        contract.list_block("7".to_string(), U128(10 * ONE_NEAR_AMOUNT));
+        contract.list_block("20".to_string(), U128(ONE_NEAR_AMOUNT));
+
+        let listings = contract.get_listings(None, None, None, None, None, None);
+        let roots: Vec<&str> = listings.iter().map(|view| view.root_id.as_str()).collect();
+        assert_eq!(roots, vec!["20", "7"]);
+        assert_eq!(listings[1].listing.area, 6);
+        assert_eq!(contract.get_listings(None, None, Some(2), None, None, None).len(), 1);
+        assert_eq!(contract.get_listings(None, Some(U128(ONE_NEAR_AMOUNT)), None, None, None, None).len(), 1);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(10 * ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.buy("7".to_string());
+
+        assert_eq!(contract.get_listing("7".to_string()), None);
+        for cell in contract.get_block_cells("7".to_string()) {
+            assert_eq!(contract.nft_token(cell).unwrap().owner_id, accounts(2));
+        }
+        assert!(test_utils::get_logs().last().unwrap().contains(r#""event":"sale""#));
+    }
+
+    #[test]
+    fn test_listing_cancelled_on_transfer() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        contract.list_block("7".to_string(), U128(ONE_NEAR_AMOUNT));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .build());
+        contract.nft_transfer(accounts(2), "7".to_string(), None, None);
+        assert_eq!(contract.get_listing("7".to_string()), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough attached to buy")]
+    fn test_market_buy_underpaid() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        contract.list_block("7".to_string(), U128(10 * ONE_NEAR_AMOUNT));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.buy("7".to_string());
+    }
+
+    #[test]
+    fn test_auction() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 3, 2);
+        contract.nft_merge(vec![rect("7", 3, 2)]);
+        contract.list_block("7".to_string(), U128(ONE_NEAR_AMOUNT));
+
+        let end_at = AUCTION_EXTENSION * 3;
+        contract.create_auction("7".to_string(), U128(ONE_NEAR_AMOUNT), U64(end_at));
+        assert_eq!(contract.get_listing("7".to_string()), None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.place_bid("7".to_string());
+
+        // a bid in the last minutes extends the auction
+        testing_env!(context
+            .block_timestamp(end_at - 1)
+            .attached_deposit(2 * ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.place_bid("7".to_string());
+        let auction = contract.get_auction("7".to_string()).unwrap();
+        assert_eq!(auction.end_at, U64(end_at - 1 + AUCTION_EXTENSION));
+        assert_eq!(auction.highest_bid.unwrap().bidder_id, accounts(3));
+
+        testing_env!(context.block_timestamp(end_at - 1 + AUCTION_EXTENSION).attached_deposit(0).build());
+        contract.settle_auction("7".to_string());
+        assert_eq!(contract.get_auction("7".to_string()), None);
+        for cell in contract.get_block_cells("7".to_string()) {
+            assert_eq!(contract.nft_token(cell).unwrap().owner_id, accounts(3));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Bid is too low")]
+    fn test_auction_min_increment() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        contract.create_auction("7".to_string(), U128(ONE_NEAR_AMOUNT), U64(AUCTION_EXTENSION * 3));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.place_bid("7".to_string());
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.place_bid("7".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Reserve price must be positive")]
+    fn test_auction_zero_reserve() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        contract.create_auction("7".to_string(), U128(0), U64(AUCTION_EXTENSION * 3));
+    }
+
+    #[test]
+    #[should_panic(expected = "Block is on auction")]
+    fn test_auction_blocks_transfer() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        contract.create_auction("7".to_string(), U128(ONE_NEAR_AMOUNT), U64(AUCTION_EXTENSION * 3));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .build());
+        contract.nft_transfer(accounts(2), "7".to_string(), None, None);
+    }
+
+    /// 3x2 zone auction running from 100 to 200.
+    fn dutch_auction(start_price: Balance, floor_price: Balance) -> DutchAuction {
+        DutchAuction {
+            width: 3,
+            height: 2,
+            start_price: U128(start_price),
+            floor_price: U128(floor_price),
+            start_at: U64(100),
+            end_at: U64(200),
+        }
+    }
+
+    #[test]
+    fn test_dutch_auction() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        contract.create_dutch_auction("7".to_string(), dutch_auction(10 * ONE_NEAR_AMOUNT, 2 * ONE_NEAR_AMOUNT));
+        assert_eq!(contract.get_dutch_auction("7".to_string()).unwrap().current_price, U128(10 * ONE_NEAR_AMOUNT));
+
+        testing_env!(context.block_timestamp(150).build());
+        let auctions = contract.get_dutch_auctions(None, None);
+        assert_eq!(auctions.len(), 1);
+        assert_eq!(auctions[0].current_price, U128(6 * ONE_NEAR_AMOUNT));
+
+        testing_env!(context
+            .block_timestamp(300)
+            .attached_deposit(3 * ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(1))
+            .build());
+        let tokens = contract.buy_dutch_auction("7".to_string(), sample_token_metadata());
+        assert_eq!(tokens.len(), 6);
+        assert!(tokens.iter().all(|token| token.owner_id == accounts(1)));
+        assert!(contract.get_dutch_auction("7".to_string()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Cell is reserved for a zone auction")]
+    fn test_dutch_auction_reserves_cells() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        contract.create_dutch_auction("7".to_string(), dutch_auction(ONE_NEAR_AMOUNT, 1));
+
+        testing_env!(context.attached_deposit(ONE_NEAR_AMOUNT).predecessor_account_id(accounts(1)).build());
+        contract.nft_mint("10".to_string(), accounts(1), sample_token_metadata());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid cell id")]
+    fn test_mint_non_canonical_id() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        contract.create_dutch_auction("7".to_string(), dutch_auction(ONE_NEAR_AMOUNT, 1));
+
+        testing_env!(context.attached_deposit(ONE_NEAR_AMOUNT).predecessor_account_id(accounts(1)).build());
+        contract.nft_mint("07".to_string(), accounts(1), sample_token_metadata());
+    }
+
+    #[test]
+    fn test_offer() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 3, 2);
+        contract.nft_merge(vec![rect("7", 3, 2)]);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(3 * ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        let low = contract.make_offer("7".to_string(), U128(ONE_NEAR_AMOUNT), U64(1000));
+        let high = contract.make_offer("7".to_string(), U128(2 * ONE_NEAR_AMOUNT), U64(1000));
+        let offers = contract.get_offers_for_block("7".to_string(), None, None);
+        assert_eq!(offers.iter().map(|view| view.offer_id).collect::<Vec<_>>(), vec![high, low]);
+        assert_eq!(contract.get_offers_by_buyer(accounts(2), None, None).len(), 2);
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.cancel_offer(low);
+        assert_eq!(contract.get_offer(low), None);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.accept_offer(high);
+        for cell in contract.get_block_cells("7".to_string()) {
+            assert_eq!(contract.nft_token(cell).unwrap().owner_id, accounts(2));
+        }
+        assert!(contract.get_offers_for_block("7".to_string(), None, None).is_empty());
+    }
+
+    #[test]
+    fn test_wish_list_offer() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+
+        testing_env!(context
+            .attached_deposit(2 * ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.make_offer("10".to_string(), U128(ONE_NEAR_AMOUNT), U64(1000));
+
+        testing_env!(context
+            .attached_deposit(6 * ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(0))
+            .build());
+        let tokens = contract.nft_batch_mint("7".to_string(), 3, 2, accounts(1), sample_token_metadata());
+        let sold = tokens.iter().find(|token| token.token_id == "10").unwrap();
+        assert_eq!(sold.owner_id, accounts(2));
+        assert_eq!(contract.nft_token("10".to_string()).unwrap().owner_id, accounts(2));
+        assert_eq!(contract.nft_token("7".to_string()).unwrap().owner_id, accounts(1));
+        assert!(contract.get_offers_by_buyer(accounts(2), None, None).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Offer is below mint price")]
+    fn test_wish_list_offer_below_mint_price() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+
+        testing_env!(context.attached_deposit(ONE_NEAR_AMOUNT).predecessor_account_id(accounts(2)).build());
+        contract.make_offer("10".to_string(), U128(1), U64(1000));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cell is reserved for a zone auction")]
+    fn test_wish_list_offer_reserved_cell() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        contract.create_dutch_auction("7".to_string(), dutch_auction(ONE_NEAR_AMOUNT, 1));
+
+        testing_env!(context.attached_deposit(2 * ONE_NEAR_AMOUNT).predecessor_account_id(accounts(2)).build());
+        contract.make_offer("10".to_string(), U128(ONE_NEAR_AMOUNT), U64(1000));
+    }
+
+    #[test]
+    fn test_dutch_auction_skips_wish_list_offers() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        testing_env!(context.attached_deposit(2 * ONE_NEAR_AMOUNT).predecessor_account_id(accounts(2)).build());
+        contract.make_offer("10".to_string(), U128(ONE_NEAR_AMOUNT), U64(1000));
+
+        testing_env!(context.attached_deposit(0).predecessor_account_id(accounts(0)).build());
+        contract.create_dutch_auction("7".to_string(), dutch_auction(ONE_NEAR_AMOUNT, 1));
+        testing_env!(context
+            .block_timestamp(300)
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(1))
+            .build());
+        let tokens = contract.buy_dutch_auction("7".to_string(), sample_token_metadata());
+        assert!(tokens.iter().all(|token| token.owner_id == accounts(1)));
+        assert_eq!(contract.nft_token("10".to_string()).unwrap().owner_id, accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Offer has expired")]
+    fn test_accept_expired_offer() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(2 * ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        let offer_id = contract.make_offer("7".to_string(), U128(ONE_NEAR_AMOUNT), U64(1000));
+
+        testing_env!(context
+            .block_timestamp(1000)
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.accept_offer(offer_id);
+    }
+
+    #[test]
+    fn test_nft_payout() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        contract.set_royalty_config(RoyaltyConfig { treasury_id: accounts(4), minter_bps: 1000, treasury_bps: 500 });
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        prepare_mint_token(&mut context, &mut contract, accounts(2), "0".to_string(), 1, 1);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).attached_deposit(1).build());
+        contract.nft_transfer(accounts(1), "0".to_string(), None, None);
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(ONE_NEAR_AMOUNT).build());
+        contract.nft_merge(vec![rect("7", 1, 2)]);
+        contract.nft_lock_block("7".to_string());
+
+        // the minter share of a block token is split between the minters of its cells
+        let payout = contract.nft_payout("block:7".to_string(), U128(10_000), None).payout;
+        assert_eq!(payout.len(), 3);
+        assert_eq!(payout[&accounts(4)], U128(500));
+        assert_eq!(payout[&accounts(2)], U128(500));
+        assert_eq!(payout[&accounts(1)], U128(9_000));
+
+        testing_env!(context.attached_deposit(1).build());
+        let payout = contract.nft_transfer_payout(accounts(3), "block:7".to_string(), None, None, U128(10_000), Some(3));
+        assert_eq!(payout.payout[&accounts(1)], U128(9_000));
+        assert_eq!(contract.nft_token("block:7".to_string()).unwrap().owner_id, accounts(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "Payout exceeds max_len_payout")]
+    fn test_nft_payout_max_len() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.nft_transfer(accounts(2), "7".to_string(), None, None);
+        contract.nft_payout("7".to_string(), U128(10_000), Some(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Lock the merged block to sell it")]
+    fn test_nft_transfer_payout_merged_cell() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 2);
+        contract.nft_merge(vec![rect("7", 1, 2)]);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.nft_transfer_payout(accounts(3), "7".to_string(), None, None, U128(10_000), None);
+    }
+
+    #[test]
+    fn test_rent_block() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        contract.set_block_image("7".to_string(), "bafyowner".to_string(), "image/png".to_string(), sample_hash());
+        let terms = RentalTerms { price_per_day: U128(ONE_NEAR_AMOUNT), min_days: 1, max_days: 30 };
+        contract.set_rental_terms("7".to_string(), terms);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(3 * ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.rent_block("7".to_string(), 2);
+        contract.set_block_image("7".to_string(), "bafyrenter".to_string(), "image/png".to_string(), sample_hash());
+        contract.set_block_link("7".to_string(), "https://renter.example".to_string(), "ad".to_string());
+        assert_eq!(contract.get_block_image("7".to_string()).unwrap().cid, "bafyrenter");
+        assert_eq!(contract.get_block_link("7".to_string()).unwrap().caption, "ad");
+        assert_eq!(contract.nft_token("7".to_string()).unwrap().owner_id, accounts(1));
+        assert_eq!(contract.get_rental_income(accounts(1)), U128(2 * ONE_NEAR_AMOUNT));
+
+        // the renter's content stops showing once the rental is over
+        testing_env!(context.block_timestamp(2 * DAY).build());
+        assert_eq!(contract.get_rental("7".to_string()), None);
+        assert_eq!(contract.get_block_image("7".to_string()).unwrap().cid, "bafyowner");
+        assert_eq!(contract.get_block_link("7".to_string()), None);
+
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        assert_eq!(contract.withdraw_rental_income(), U128(2 * ONE_NEAR_AMOUNT));
+        assert_eq!(contract.get_rental_income(accounts(1)), U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Rental duration out of terms")]
+    fn test_rent_block_duration() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        let terms = RentalTerms { price_per_day: U128(ONE_NEAR_AMOUNT), min_days: 7, max_days: 30 };
+        contract.set_rental_terms("7".to_string(), terms);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(3 * ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.rent_block("7".to_string(), 2);
+    }
+
+    #[test]
+    fn test_book_block() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        let terms = RentalTerms { price_per_day: U128(ONE_NEAR_AMOUNT), min_days: 1, max_days: 30 };
+        contract.set_rental_terms("7".to_string(), terms);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(3 * ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_block("7".to_string(), U64(10 * DAY), 2);
+        let image = BlockImage { cid: "bafyad".to_string(), mime: "image/png".to_string(), hash: sample_hash() };
+        contract.set_booking_content("7".to_string(), U64(10 * DAY), Some(image), None);
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.book_block("7".to_string(), U64(5 * DAY), 1);
+        contract.book_block("7".to_string(), U64(8 * DAY), 1);
+
+        let bookings = contract.get_bookings("7".to_string(), None, None);
+        let starts: Vec<U64> = bookings.iter().map(|booking| booking.start_at).collect();
+        assert_eq!(starts, vec![U64(5 * DAY), U64(8 * DAY), U64(10 * DAY)]);
+        // nothing is credited before a booking starts
+        assert_eq!(contract.get_rental_income(accounts(1)), U128(0));
+
+        let content = contract.get_block_content_at("7".to_string(), U64(11 * DAY));
+        assert_eq!(content.renter_id, Some(accounts(2)));
+        assert_eq!(content.image.unwrap().cid, "bafyad");
+        assert_eq!(contract.get_block_content_at("7".to_string(), U64(12 * DAY)).renter_id, None);
+
+        // full refund with a week of notice, half of it later
+        testing_env!(context.attached_deposit(1).build());
+        contract.cancel_booking("7".to_string(), U64(8 * DAY));
+        assert_eq!(contract.get_rental_income(accounts(1)), U128(0));
+        testing_env!(context.block_timestamp(DAY).build());
+        contract.cancel_booking("7".to_string(), U64(5 * DAY));
+        assert!(test_utils::get_logs().last().unwrap().contains(r#""refund":"500000000000000000000000""#));
+        assert_eq!(contract.get_rental_income(accounts(1)), U128(ONE_NEAR_AMOUNT / 2));
+
+        testing_env!(context.block_timestamp(10 * DAY).build());
+        contract.settle_bookings("7".to_string());
+        assert_eq!(contract.get_rental_income(accounts(1)), U128(ONE_NEAR_AMOUNT / 2 + 2 * ONE_NEAR_AMOUNT));
+        assert_eq!(contract.get_block_image("7".to_string()).unwrap().cid, "bafyad");
+    }
+
+    #[test]
+    #[should_panic(expected = "Block is already rented")]
+    fn test_book_block_overlap() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        let terms = RentalTerms { price_per_day: U128(ONE_NEAR_AMOUNT), min_days: 1, max_days: 30 };
+        contract.set_rental_terms("7".to_string(), terms);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(3 * ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_block("7".to_string(), U64(DAY), 2);
+        contract.book_block("7".to_string(), U64(2 * DAY), 1);
+    }
+
+    #[test]
+    fn test_harberger() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        // 10% a year
+        contract.set_harberger_tax_rate(1000);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(ONE_NEAR_AMOUNT).build());
+        contract.declare_valuation("7".to_string(), U128(10 * ONE_NEAR_AMOUNT));
+        let account = contract.get_harberger_account("7".to_string()).unwrap();
+        assert!(account.deposit.0 > 0 && account.deposit.0 < ONE_NEAR_AMOUNT);
+
+        testing_env!(context.block_timestamp(YEAR / 10).build());
+        let account = contract.get_harberger_account("7".to_string()).unwrap();
+        assert!(!account.delinquent);
+
+        testing_env!(context
+            .attached_deposit(12 * ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.force_buy("7".to_string(), U128(20 * ONE_NEAR_AMOUNT));
+        assert_eq!(contract.nft_token("7".to_string()).unwrap().owner_id, accounts(2));
+        let account = contract.get_harberger_account("7".to_string()).unwrap();
+        assert_eq!(account.valuation, U128(20 * ONE_NEAR_AMOUNT));
+        // the storage of the new account is taken from the 2 NEAR left over
+        assert!(account.deposit.0 > ONE_NEAR_AMOUNT && account.deposit.0 < 2 * ONE_NEAR_AMOUNT);
+
+        // 2 NEAR covers a year of tax on 20 NEAR, after that the block goes to the treasury
+        testing_env!(context.block_timestamp(YEAR / 10 + YEAR + 1).attached_deposit(0).build());
+        assert!(contract.get_harberger_account("7".to_string()).unwrap().delinquent);
+        contract.seize_block("7".to_string());
+        assert_eq!(contract.nft_token("7".to_string()).unwrap().owner_id, accounts(0));
+        assert_eq!(contract.get_harberger_account("7".to_string()), None);
+    }
+
+    #[test]
+    fn test_harberger_tax_rate_change() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        contract.set_harberger_tax_rate(1000);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(ONE_NEAR_AMOUNT).build());
+        contract.declare_valuation("7".to_string(), U128(10 * ONE_NEAR_AMOUNT));
+        let deposit = contract.get_harberger_account("7".to_string()).unwrap().deposit.0;
+
+        // a tenth of a year at 10% owes 0.1 NEAR, switching the mode off keeps it owed
+        testing_env!(context.block_timestamp(YEAR / 10).predecessor_account_id(accounts(0)).build());
+        contract.set_harberger_tax_rate(0);
+        assert!(test_utils::get_logs().last().unwrap().contains("harberger_tax_rate_update"));
+        testing_env!(context.block_timestamp(YEAR / 5).build());
+        let account = contract.get_harberger_account("7".to_string()).unwrap();
+        assert_eq!(account.deposit.0, deposit - ONE_NEAR_AMOUNT / 10);
+
+        // raising the rate again only applies from now on
+        contract.set_harberger_tax_rate(5000);
+        testing_env!(context.block_timestamp(YEAR / 5 + YEAR / 100).build());
+        let account = contract.get_harberger_account("7".to_string()).unwrap();
+        assert_eq!(account.deposit.0, deposit - ONE_NEAR_AMOUNT / 10 - ONE_NEAR_AMOUNT / 20);
+        assert!(!account.delinquent);
+    }
+
+    #[test]
+    fn test_harberger_transfer() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        contract.set_harberger_tax_rate(1000);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(ONE_NEAR_AMOUNT).build());
+        contract.declare_valuation("7".to_string(), U128(10 * ONE_NEAR_AMOUNT));
+        assert_eq!(contract.get_harberger_account("7".to_string()).unwrap().owner_id, accounts(1));
+
+        testing_env!(context.block_timestamp(YEAR / 10).attached_deposit(1).build());
+        contract.nft_transfer(accounts(2), "7".to_string(), None, None);
+        assert_eq!(contract.get_harberger_account("7".to_string()), None);
+    }
+
+    #[test]
+    fn test_force_buy_cancels_auction() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        contract.set_harberger_tax_rate(1000);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(ONE_NEAR_AMOUNT).build());
+        contract.declare_valuation("7".to_string(), U128(2 * ONE_NEAR_AMOUNT));
+        contract.create_auction("7".to_string(), U128(ONE_NEAR_AMOUNT), U64(AUCTION_EXTENSION * 3));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.place_bid("7".to_string());
+
+        testing_env!(context.attached_deposit(3 * ONE_NEAR_AMOUNT).predecessor_account_id(accounts(2)).build());
+        contract.force_buy("7".to_string(), U128(2 * ONE_NEAR_AMOUNT));
+        assert_eq!(contract.get_auction("7".to_string()), None);
+        assert_eq!(contract.nft_token("7".to_string()).unwrap().owner_id, accounts(2));
+        assert!(test_utils::get_logs().iter().any(|log| log.contains("auction_cancel")));
+    }
+
+    #[test]
+    fn test_force_buy_undeclared() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 3, 2);
+        contract.nft_merge(vec![rect("7", 3, 2)]);
+        assert_eq!(contract.get_block_valuation("7".to_string()), None);
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.set_harberger_tax_rate(1000);
+        assert_eq!(contract.get_block_valuation("7".to_string()), Some(U128(6 * ONE_NEAR_AMOUNT)));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(7 * ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.force_buy("7".to_string(), U128(10 * ONE_NEAR_AMOUNT));
+        assert_eq!(contract.nft_token("7".to_string()).unwrap().owner_id, accounts(2));
+        assert_eq!(contract.get_block_valuation("7".to_string()), Some(U128(10 * ONE_NEAR_AMOUNT)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough attached to buy")]
+    fn test_force_buy_undeclared_below_default() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        contract.set_harberger_tax_rate(1000);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 2);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(ONE_NEAR_AMOUNT / 2)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.force_buy("0".to_string(), U128(ONE_NEAR_AMOUNT));
+    }
+
+    #[test]
+    fn test_harberger_merge() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        contract.set_harberger_tax_rate(1000);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 2);
+        contract.declare_valuation("7".to_string(), U128(3 * ONE_NEAR_AMOUNT));
+        contract.declare_valuation("0".to_string(), U128(4 * ONE_NEAR_AMOUNT));
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(ONE_NEAR_AMOUNT).build());
+        contract.nft_merge(vec![rect("7", 1, 2)]);
+        assert_eq!(contract.get_harberger_account("0".to_string()), None);
+        assert_eq!(contract.get_harberger_account("7".to_string()).unwrap().valuation, U128(7 * ONE_NEAR_AMOUNT));
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(ONE_NEAR_AMOUNT).build());
+        contract.nft_unmerge("7".to_string());
+        assert_eq!(contract.get_harberger_account("7".to_string()).unwrap().valuation, U128(7 * ONE_NEAR_AMOUNT / 2));
+        assert_eq!(contract.get_harberger_account("0".to_string()).unwrap().valuation, U128(7 * ONE_NEAR_AMOUNT / 2));
+    }
+
+    #[test]
+    fn test_harberger_merge_storage() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        contract.set_harberger_tax_rate(1000);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 3, 2);
+        for cell in ["7", "8", "9", "0", "1", "10"] {
+            testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(ONE_NEAR_AMOUNT).build());
+            contract.declare_valuation(cell.to_string(), U128(ONE_NEAR_AMOUNT));
+        }
+
+        // folding six accounts into one releases more storage than the merge takes
+        let initial_storage_usage = env::storage_usage();
+        testing_env!(context.storage_usage(initial_storage_usage).attached_deposit(ONE_NEAR_AMOUNT).build());
+        contract.nft_merge(vec![rect("7", 3, 2)]);
+        assert!(env::storage_usage() < initial_storage_usage);
+        assert_eq!(contract.get_harberger_account("7".to_string()).unwrap().valuation, U128(6 * ONE_NEAR_AMOUNT));
+
+        // splitting the account over six cells takes more storage than the unmerge releases
+        let initial_storage_usage = env::storage_usage();
+        testing_env!(context.storage_usage(initial_storage_usage).attached_deposit(ONE_NEAR_AMOUNT).build());
+        contract.nft_unmerge("7".to_string());
+        assert!(env::storage_usage() > initial_storage_usage);
+        assert_eq!(contract.get_harberger_account("10".to_string()).unwrap().valuation, U128(ONE_NEAR_AMOUNT));
+    }
+
+    #[test]
+    #[should_panic(expected = "Block is not delinquent")]
+    fn test_seize_block_not_delinquent() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        contract.set_harberger_tax_rate(1000);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        contract.declare_valuation("7".to_string(), U128(ONE_NEAR_AMOUNT));
+        contract.seize_block("7".to_string());
+    }
+
+    /// Fractionalize the block rooted at `root_id` owned by `accounts(1)` into `total_supply` shares.
+    fn fractionalize(context: &mut VMContextBuilder, contract: &mut Contract, root_id: &str, total_supply: u128) {
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.set_vault_token_code(Base64VecU8(vec![0; 100]));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.fractionalize_block(root_id.to_string(), U128(total_supply));
+    }
+
+    /// Send `amount` shares of the vault of "7" from `sender_id` to the contract as its share token
+    /// would with `ft_transfer_call`.
+    fn send_shares(context: &mut VMContextBuilder, contract: &mut Contract, sender_id: AccountId, amount: u128, msg: &str) {
+        let token_account_id = contract.get_vault("7".to_string()).unwrap().token_account_id;
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(token_account_id)
+            .build());
+        contract.ft_on_transfer(sender_id, U128(amount), msg.to_string());
+    }
+
+    #[test]
+    fn test_vault() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 3, 2);
+        contract.nft_merge(vec![rect("7", 3, 2)]);
+        contract.nft_lock_block("7".to_string());
+        fractionalize(&mut context, &mut contract, "7", 100);
+        assert_eq!(contract.nft_token("block:7".to_string()).unwrap().owner_id, env::current_account_id());
+        let token_account_id: AccountId = "v7-0.alice".parse().unwrap();
+        assert_eq!(contract.get_vault("7".to_string()).unwrap().token_account_id, token_account_id);
+        assert!(test_utils::get_created_receipts().iter().any(|receipt| receipt.receiver_id == token_account_id));
+
+        // 40% of the shares is not a majority, 60% is
+        let msg = r#"{"action":"propose","root_id":"7","image":{"cid":"bafyvault","mime":"image/png","hash":"AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="}}"#;
+        send_shares(&mut context, &mut contract, accounts(2), 40, msg);
+        assert_eq!(contract.get_block_image("7".to_string()), None);
+        assert_eq!(contract.get_vault("7".to_string()).unwrap().proposal.unwrap().votes, U128(40));
+        send_shares(&mut context, &mut contract, accounts(3), 20, r#"{"action":"vote","root_id":"7"}"#);
+        assert_eq!(contract.get_block_image("7".to_string()).unwrap().cid, "bafyvault");
+        assert!(contract.get_vault("7".to_string()).unwrap().proposal.is_none());
+
+        testing_env!(context.attached_deposit(1).predecessor_account_id(accounts(2)).build());
+        contract.withdraw_vault_shares("7".to_string());
+        assert_eq!(contract.get_vault_locked_shares("7".to_string(), accounts(2)), None);
+        assert_eq!(
+            contract.get_vault_locked_shares("7".to_string(), accounts(3)),
+            Some(LockedShares { proposal_id: Some(0), amount: U128(20) })
+        );
+
+        // buyout once a single holder sends every share
+        send_shares(&mut context, &mut contract, accounts(1), 100, r#"{"action":"redeem","root_id":"7"}"#);
+        assert_eq!(contract.nft_token("block:7".to_string()).unwrap().owner_id, accounts(1));
+        assert!(contract.get_vault("7".to_string()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "A proposal is already open")]
+    fn test_vault_propose_while_open() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        fractionalize(&mut context, &mut contract, "7", 100);
+        let msg = r#"{"action":"propose","root_id":"7","image":{"cid":"bafyvault","mime":"image/png","hash":"AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="}}"#;
+        send_shares(&mut context, &mut contract, accounts(2), 40, msg);
+        send_shares(&mut context, &mut contract, accounts(3), 1, msg);
+    }
+
+    #[test]
+    fn test_vault_propose_after_expiry() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        fractionalize(&mut context, &mut contract, "7", 100);
+        let msg = r#"{"action":"propose","root_id":"7","image":{"cid":"bafyvault","mime":"image/png","hash":"AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="}}"#;
+        send_shares(&mut context, &mut contract, accounts(2), 40, msg);
+
+        context.block_timestamp(VAULT_PROPOSAL_DURATION);
+        send_shares(&mut context, &mut contract, accounts(3), 10, msg);
+        let proposal = contract.get_vault("7".to_string()).unwrap().proposal.unwrap();
+        assert_eq!(proposal.proposal_id, 1);
+        assert_eq!(proposal.proposer_id, accounts(3));
+        assert_eq!(proposal.votes, U128(10));
+    }
+
+    #[test]
+    fn test_vault_after_redeem() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        fractionalize(&mut context, &mut contract, "7", 100);
+        send_shares(&mut context, &mut contract, accounts(1), 100, r#"{"action":"redeem","root_id":"7"}"#);
+        assert_eq!(contract.nft_token("7".to_string()).unwrap().owner_id, accounts(1));
+
+        // the first share token still exists, the new vault gets its own
+        fractionalize(&mut context, &mut contract, "7", 50);
+        let vault = contract.get_vault("7".to_string()).unwrap();
+        assert_eq!(vault.token_account_id, "v7-1.alice".parse::<AccountId>().unwrap());
+        assert_eq!(vault.total_supply, U128(50));
+    }
+
+    #[test]
+    fn test_vault_token_deploy_failed() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        fractionalize(&mut context, &mut contract, "7", 100);
+
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![near_sdk::PromiseResult::Failed],
+        );
+        contract.resolve_vault_token("7".to_string(), accounts(1), U128(1));
+        assert_eq!(contract.nft_token("7".to_string()).unwrap().owner_id, accounts(1));
+        assert!(contract.get_vault("7".to_string()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Not the share token of the vault")]
+    fn test_vault_shares_from_other_token() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        fractionalize(&mut context, &mut contract, "7", 100);
+
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(4), U128(100), r#"{"action":"redeem","root_id":"7"}"#.to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Block is in a vault")]
+    fn test_vault_force_buy() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        contract.set_harberger_tax_rate(1000);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(ONE_NEAR_AMOUNT).build());
+        contract.declare_valuation("7".to_string(), U128(ONE_NEAR_AMOUNT));
+        fractionalize(&mut context, &mut contract, "7", 100);
+        assert_eq!(contract.get_harberger_account("7".to_string()), None);
+        assert_eq!(contract.get_block_valuation("7".to_string()), None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(2 * ONE_NEAR_AMOUNT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.force_buy("7".to_string(), U128(ONE_NEAR_AMOUNT));
+    }
+
+    #[test]
+    #[should_panic(expected = "Must send all shares to redeem")]
+    fn test_redeem_vault_partial() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        fractionalize(&mut context, &mut contract, "7", 100);
+        send_shares(&mut context, &mut contract, accounts(1), 99, r#"{"action":"redeem","root_id":"7"}"#);
+    }
+
+    #[test]
+    fn test_stake_block() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 3, 2);
+        contract.nft_merge(vec![rect("7", 3, 2)]);
+        contract.stake_block("7".to_string());
+        // six center cells counting double, raised by the merge bonus
+        let stake = contract.get_stake("7".to_string()).unwrap();
+        assert_eq!(stake.stake.weight, U128(6 * 20_000 * 12 / 10));
+
+        testing_env!(context.block_timestamp(2 * EPOCH).build());
+        assert_eq!(contract.get_stake("7".to_string()).unwrap().pending_rewards, U128(2 * 100 * 6 * 2 * 12 / 10));
+        assert_eq!(contract.claim_rewards("7".to_string()), U128(2880));
+
+        // halving the rate only affects rewards from now on
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.set_reward_rate(U128(50));
+        testing_env!(context.block_timestamp(3 * EPOCH).attached_deposit(1).predecessor_account_id(accounts(1)).build());
+        contract.unstake_block("7".to_string());
+        assert_eq!(contract.get_reward_points(accounts(1)), U128(2880 + 720));
+        assert!(contract.get_stake("7".to_string()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Stake the block by its root id")]
+    fn test_stake_block_token_twice() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 3, 2);
+        contract.nft_merge(vec![rect("7", 3, 2)]);
+        contract.nft_lock_block("7".to_string());
+        contract.stake_block("7".to_string());
+        contract.stake_block("block:7".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Block is staked")]
+    fn test_staked_block_transfer() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "7".to_string(), 1, 1);
+        contract.stake_block("7".to_string());
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.nft_transfer(accounts(2), "7".to_string(), None, None);
+    }
+
+    #[test]
+    fn test_merge_views() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0), ONE_NEAR_AMOUNT);
+
+        prepare_mint_token(&mut context, &mut contract, accounts(1), "19".to_string(), 2, 3);
+        prepare_mint_token(&mut context, &mut contract, accounts(0), "7".to_string(), 3, 2);
+        contract.nft_merge(vec![rect("7", 2, 2)]);
+
+        assert_eq!(contract.get_merge_root("1".to_string()), Some("7".to_string()));
+        assert_eq!(contract.get_merge_root("7".to_string()), Some("7".to_string()));
+        assert_eq!(contract.get_merge_root("9".to_string()), Some("9".to_string()));
+        assert_eq!(contract.get_merge_root("100".to_string()), None);
+
+        assert_eq!(contract.get_block_cells("7".to_string()), vec!["7", "0", "8", "1"]);
+        assert_eq!(contract.get_block_cells("9".to_string()), vec!["9"]);
+
+        let mut blocks: Vec<TokenId> = contract
+            .get_blocks_for_owner(accounts(0), None, None)
+            .into_iter()
+            .map(|block| block.token_id)
+            .collect();
+        blocks.sort();
+        assert_eq!(blocks, vec!["10", "7", "9"]);
+
+        assert_eq!(contract.get_blocks_for_owner(accounts(0), Some(U128(1)), Some(1)).len(), 1);
+        assert_eq!(contract.get_blocks_for_owner(accounts(1), None, None).len(), 6);
+        assert!(contract.get_blocks_for_owner(accounts(2), None, None).is_empty());
+    }
+
     #[test]
     fn test_get_not_covered_tokens() {
         let mut context = get_context(accounts(0));
@@ -294,23 +2283,23 @@ mod tests {
 
         // bob
         prepare_mint_token(&mut context, &mut contract, accounts(1), "19".to_string(), 2, 3);
-        contract.nft_merge("18".to_string(), 2, 2);
+        contract.nft_merge(vec![rect("18", 2, 2)]);
 
         // alice
         prepare_mint_token(&mut context, &mut contract, accounts(0), "7".to_string(), 3, 2);
-        contract.nft_merge("7".to_string(), 2, 2);
-        contract.nft_merge("9".to_string(), 1, 2);
+        contract.nft_merge(vec![rect("7", 2, 2)]);
+        contract.nft_merge(vec![rect("9", 1, 2)]);
 
         let mut tokens = contract.get_not_covered_tokens();
         tokens.sort_by(|a, b| a.token_id.parse::<usize>().unwrap().cmp(&b.token_id.parse::<usize>().unwrap()));
         println!("{:?}", tokens);
 
         let expected = vec![
-            UncoveredToken { token_id: "6".to_string(), width: 1, height: 1 },
-            UncoveredToken { token_id: "7".to_string(), width: 2, height: 2 },
-            UncoveredToken { token_id: "9".to_string(), width: 1, height: 2 },
-            UncoveredToken { token_id: "18".to_string(), width: 2, height: 2 },
-            UncoveredToken { token_id: "19".to_string(), width: 1, height: 1 }
+            TokenShape { token_id: "6".to_string(), rects: vec![rect("6", 1, 1)] },
+            TokenShape { token_id: "7".to_string(), rects: vec![rect("7", 2, 2)] },
+            TokenShape { token_id: "9".to_string(), rects: vec![rect("9", 1, 2)] },
+            TokenShape { token_id: "18".to_string(), rects: vec![rect("18", 2, 2)] },
+            TokenShape { token_id: "19".to_string(), rects: vec![rect("19", 1, 1)] }
         ];
 
         assert_eq!(tokens, expected);
@@ -383,7 +2372,7 @@ mod tests {
         if let Some(token) = contract.nft_token(token_id.clone()) {
             assert_eq!(token.token_id, token_id);
             assert_eq!(token.owner_id, accounts(1));
-            assert_eq!(token.metadata.unwrap(), sample_token_metadata());
+            assert_eq!(token.metadata.unwrap().description, sample_token_metadata().description);
             assert_eq!(token.approved_account_ids.unwrap(), HashMap::new());
         } else {
             panic!("token not correctly created, or not found by nft_token");
@@ -0,0 +1,69 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen};
+
+use crate::events::MergeLimitsUpdate;
+use crate::*;
+
+/// Limits on the size and proportions of a merged shape, checked against the bounding box of
+/// the shape before any cell is visited.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MergeLimits {
+    /// Maximum number of cells in one merge.
+    pub max_area: u32,
+    pub max_width: u8,
+    pub max_height: u8,
+    /// Maximum ratio between the long and the short side, e.g. 4 allows up to 4:1.
+    pub max_aspect_ratio: u8,
+}
+
+impl Default for MergeLimits {
+    fn default() -> Self {
+        Self {
+            max_area: 400,
+            max_width: 40,
+            max_height: 40,
+            max_aspect_ratio: 10,
+        }
+    }
+}
+
+impl MergeLimits {
+    pub fn assert_valid(&self) {
+        assert!(
+            self.max_area > 0 && self.max_width > 0 && self.max_height > 0 && self.max_aspect_ratio > 0,
+            "Invalid merge limits"
+        );
+    }
+
+    pub fn assert_shape_allowed(&self, rects: &[MergeRect]) {
+        let area: u32 = rects.iter().map(|rect| rect.width as u32 * rect.height as u32).sum();
+        assert!(area <= self.max_area, "Merge area exceeds {}", self.max_area);
+
+        let (width, height) = shape_size(rects);
+        assert!(width <= self.max_width as u64, "Merge width exceeds {}", self.max_width);
+        assert!(height <= self.max_height as u64, "Merge height exceeds {}", self.max_height);
+
+        let (long_side, short_side) = if width > height { (width, height) } else { (height, width) };
+        assert!(
+            long_side <= short_side * self.max_aspect_ratio as u64,
+            "Merge aspect ratio exceeds {}:1", self.max_aspect_ratio
+        );
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Owner only, replace the limits enforced by `nft_merge`.
+    pub fn set_merge_limits(&mut self, merge_limits: MergeLimits) {
+        assert_eq!(env::predecessor_account_id(), self.tokens.owner_id, "Unauthorized");
+        merge_limits.assert_valid();
+        self.merge_limits = merge_limits;
+        MergeLimitsUpdate { merge_limits: &self.merge_limits }.emit();
+    }
+
+    pub fn get_merge_limits(&self) -> MergeLimits {
+        self.merge_limits.clone()
+    }
+}
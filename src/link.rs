@@ -0,0 +1,67 @@
+use near_contract_standards::non_fungible_token::{refund_deposit, TokenId};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen};
+
+use crate::events::PixelLinkSet;
+use crate::*;
+
+const MAX_URL_LEN: usize = 256;
+const MAX_CAPTION_LEN: usize = 100;
+
+/// Where a click on a block leads, with a caption used as alt text.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlockLink {
+    pub url: String,
+    pub caption: String,
+}
+
+impl BlockLink {
+    pub fn assert_valid(&self) {
+        assert!(self.url.len() <= MAX_URL_LEN, "Url is too long");
+        assert!(self.url.starts_with("https://"), "Url must use https");
+        let host = &self.url["https://".len()..];
+        assert!(!host.is_empty() && !host.contains(char::is_whitespace), "Invalid url");
+        assert!(self.caption.chars().count() <= MAX_CAPTION_LEN, "Caption is too long");
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Set the link and caption of the block rooted at `root_id`. Only the block owner, or the
+    /// renter while the block is rented, can set it. The owner's link is cleared when the block
+    /// changes hands. The attached deposit must cover the storage used, the excess is refunded.
+    #[payable]
+    pub fn set_block_link(&mut self, root_id: TokenId, url: String, caption: String) {
+        let initial_storage_usage = env::storage_usage();
+        let owner_id = env::predecessor_account_id();
+        let renting = self.is_active_renter(&root_id, &owner_id);
+        if !renting {
+            assert_eq!(self.internal_block_owner(&root_id), owner_id, "No permission to set link");
+        }
+
+        let link = BlockLink { url, caption };
+        link.assert_valid();
+        match self.internal_active_rental(&root_id) {
+            Some(mut rental) if renting => {
+                rental.link = Some(link.clone());
+                self.internal_update_active_rental(&root_id, rental);
+            }
+            _ => {
+                self.block_links.insert(&root_id, &link);
+            }
+        }
+        PixelLinkSet { owner_id: &owner_id, root_id: &root_id, url: &link.url, caption: &link.caption }.emit();
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage));
+    }
+
+    /// Link of the block rooted at `root_id`, `None` while the block is hidden by a moderator.
+    pub fn get_block_link(&self, root_id: TokenId) -> Option<BlockLink> {
+        if self.is_hidden_block(&root_id) {
+            return None;
+        }
+        self.internal_block_link(&root_id)
+    }
+}
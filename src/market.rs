@@ -0,0 +1,164 @@
+use near_contract_standards::non_fungible_token::{refund_deposit, TokenId};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Balance, Promise};
+
+use crate::events::{Delisting, Listing, MarketFeeUpdate, Sale};
+use crate::*;
+
+/// Fees are expressed in basis points, 10_000 is 100%.
+pub const FEE_DENOMINATOR: u128 = 10_000;
+/// Default cut of every sale kept by the contract owner, 2.5%.
+pub const DEFAULT_MARKET_FEE: u16 = 250;
+
+/// A block offered for sale at a fixed price.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlockListing {
+    pub seller_id: AccountId,
+    pub price: U128,
+    /// Number of cells in the block.
+    pub area: u32,
+    pub listed_at: U64,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ListingView {
+    pub root_id: TokenId,
+    pub listing: BlockListing,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Offer the block rooted at `root_id` for sale at `price`, replacing a previous listing.
+    /// The attached deposit must cover the storage used, the excess is refunded.
+    #[payable]
+    pub fn list_block(&mut self, root_id: TokenId, price: U128) {
+        let initial_storage_usage = env::storage_usage();
+        let seller_id = self.internal_block_owner(&root_id);
+        assert_eq!(seller_id, env::predecessor_account_id(), "No permission to list");
+        assert!(price.0 > 0, "Price must be positive");
+        self.assert_not_on_auction(&root_id);
+        self.assert_not_staked(&root_id);
+
+        let area = self.get_block_cells(root_id.clone()).len() as u32;
+        self.listings.insert(&root_id, &BlockListing {
+            seller_id: seller_id.clone(),
+            price,
+            area,
+            listed_at: U64(env::block_timestamp()),
+        });
+        Listing { seller_id: &seller_id, root_id: &root_id, price }.emit();
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage));
+    }
+
+    #[payable]
+    pub fn delist(&mut self, root_id: TokenId) {
+        assert_one_yocto();
+        let listing = self.listings.get(&root_id).unwrap_or_else(|| env::panic_str("Block is not listed"));
+        assert_eq!(listing.seller_id, env::predecessor_account_id(), "No permission to delist");
+        self.internal_remove_listing(&root_id);
+    }
+
+    /// Buy the listed block rooted at `root_id`: every cell (or the block token) moves to the
+    /// caller, the seller is paid the price minus the market fee and royalties and the excess
+    /// deposit is refunded.
+    #[payable]
+    pub fn buy(&mut self, root_id: TokenId) {
+        let listing = self.listings.get(&root_id).unwrap_or_else(|| env::panic_str("Block is not listed"));
+        let buyer_id = env::predecessor_account_id();
+        let deposit = env::attached_deposit();
+        assert_ne!(buyer_id, listing.seller_id, "Seller cannot buy own block");
+        assert!(deposit >= listing.price.0, "Not enough attached to buy");
+
+        self.listings.remove(&root_id);
+        self.internal_transfer_block(&root_id, &listing.seller_id, &buyer_id, "buy");
+
+        let fee = self.internal_pay_sale(&root_id, &listing.seller_id, listing.price.0);
+        if deposit > listing.price.0 {
+            Promise::new(buyer_id.clone()).transfer(deposit - listing.price.0);
+        }
+
+        Sale {
+            seller_id: &listing.seller_id,
+            buyer_id: &buyer_id,
+            root_id: &root_id,
+            price: listing.price,
+            fee: U128(fee),
+        }
+        .emit();
+    }
+
+    /// Owner only, set the cut of every sale in basis points.
+    pub fn set_market_fee(&mut self, market_fee: u16) {
+        assert_eq!(env::predecessor_account_id(), self.tokens.owner_id, "Unauthorized");
+        assert!(market_fee as u128 <= FEE_DENOMINATOR, "Invalid market fee");
+        MarketFeeUpdate { old_fee: self.market_fee, new_fee: market_fee }.emit();
+        self.market_fee = market_fee;
+    }
+
+    pub fn get_market_fee(&self) -> u16 {
+        self.market_fee
+    }
+
+    pub fn get_listing(&self, root_id: TokenId) -> Option<BlockListing> {
+        self.listings.get(&root_id)
+    }
+
+    /// Listings matching the price and area bounds, cheapest first.
+    pub fn get_listings(
+        &self,
+        min_price: Option<U128>,
+        max_price: Option<U128>,
+        min_area: Option<u32>,
+        max_area: Option<u32>,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<ListingView> {
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+        let min_price = min_price.map(|v| v.0).unwrap_or(0);
+        let max_price = max_price.map(|v| v.0).unwrap_or(u128::MAX);
+        let min_area = min_area.unwrap_or(0);
+        let max_area = max_area.unwrap_or(u32::MAX);
+
+        let mut listings: Vec<ListingView> = self.listings
+            .iter()
+            .filter(|(_, listing)| {
+                listing.price.0 >= min_price && listing.price.0 <= max_price
+                    && listing.area >= min_area && listing.area <= max_area
+            })
+            .map(|(root_id, listing)| ListingView { root_id, listing })
+            .collect();
+        listings.sort_by_key(|view| view.listing.price.0);
+
+        listings.into_iter().skip(start_index as usize).take(limit).collect()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_remove_listing(&mut self, root_id: &TokenId) {
+        if self.listings.remove(root_id).is_some() {
+            Delisting { root_id }.emit();
+        }
+    }
+
+    /// Pay the proceeds of selling the block rooted at `root_id` at `price`: the market fee goes to
+    /// the contract owner, royalties are paid out of the rest and the remainder goes to `seller_id`.
+    /// Returns the fee.
+    pub(crate) fn internal_pay_sale(&mut self, root_id: &TokenId, seller_id: &AccountId, price: Balance) -> Balance {
+        let fee = price * self.market_fee as u128 / FEE_DENOMINATOR;
+        if fee > 0 {
+            Promise::new(self.tokens.owner_id.clone()).transfer(fee);
+        }
+        let cells = self.get_block_cells(root_id.clone());
+        for (account_id, amount) in self.internal_payout(root_id, &cells, seller_id, price - fee) {
+            Promise::new(account_id).transfer(amount);
+        }
+        fee
+    }
+}
@@ -0,0 +1,190 @@
+use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Balance, Promise};
+
+use crate::events::{BlockAppeal, BlockFlag, BlockHide, BlockRestore, ModerationDismiss, ModeratorAdd, ModeratorRemove};
+use crate::*;
+
+/// Deposit required to flag a block, refunded when the report leads to a takedown.
+pub const FLAG_DEPOSIT: Balance = 100_000_000_000_000_000_000_000;
+/// Time an account waits between two flags, 1 hour.
+pub const FLAG_INTERVAL: u64 = 60 * 60 * 1_000_000_000;
+/// Maximum number of open flags on one block.
+const MAX_FLAGS_PER_BLOCK: usize = 20;
+const MAX_APPEAL_LEN: usize = 280;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Flag {
+    pub flagger_id: AccountId,
+    pub reason_code: u8,
+    pub deposit: U128,
+    pub flagged_at: U64,
+}
+
+/// Open moderation work on a block: user reports and the owner's appeal of a takedown.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ModerationCase {
+    pub flags: Vec<Flag>,
+    pub appeal: Option<String>,
+}
+
+/// Image and link of a block hidden by a moderator.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Takedown {
+    pub moderator_id: AccountId,
+    pub reason_code: u8,
+    pub hidden_at: U64,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ModerationQueueItem {
+    pub root_id: TokenId,
+    pub case: ModerationCase,
+    pub takedown: Option<Takedown>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Owner only, allow `account_id` to hide and restore block content.
+    pub fn add_moderator(&mut self, account_id: AccountId) {
+        assert_eq!(env::predecessor_account_id(), self.tokens.owner_id, "Unauthorized");
+        self.moderators.insert(&account_id);
+        ModeratorAdd { account_id: &account_id }.emit();
+    }
+
+    /// Owner only.
+    pub fn remove_moderator(&mut self, account_id: AccountId) {
+        assert_eq!(env::predecessor_account_id(), self.tokens.owner_id, "Unauthorized");
+        self.moderators.remove(&account_id);
+        ModeratorRemove { account_id: &account_id }.emit();
+    }
+
+    pub fn is_moderator(&self, account_id: AccountId) -> bool {
+        self.moderators.contains(&account_id)
+    }
+
+    /// Report the content of the block rooted at `root_id`. Requires `FLAG_DEPOSIT`, which is
+    /// refunded if a moderator hides the block and kept otherwise. An account can flag once per
+    /// `FLAG_INTERVAL`.
+    #[payable]
+    pub fn flag_block(&mut self, root_id: TokenId, reason_code: u8) {
+        let deposit = env::attached_deposit();
+        assert!(deposit >= FLAG_DEPOSIT, "Requires a deposit of {} yoctoNEAR to flag", FLAG_DEPOSIT);
+        self.internal_block_owner(&root_id);
+
+        let flagger_id = env::predecessor_account_id();
+        let now = env::block_timestamp();
+        if let Some(flagged_at) = self.last_flag_at.get(&flagger_id) {
+            assert!(now >= flagged_at + FLAG_INTERVAL, "Flagging too often");
+        }
+        self.last_flag_at.insert(&flagger_id, &now);
+
+        let mut case = self.moderation_queue.get(&root_id).unwrap_or_default();
+        assert!(case.flags.len() < MAX_FLAGS_PER_BLOCK, "Block already has too many flags");
+        assert!(case.flags.iter().all(|flag| flag.flagger_id != flagger_id), "Block already flagged");
+        case.flags.push(Flag { flagger_id: flagger_id.clone(), reason_code, deposit: U128(deposit), flagged_at: U64(now) });
+        self.moderation_queue.insert(&root_id, &case);
+
+        BlockFlag { flagger_id: &flagger_id, root_id: &root_id, reason_code }.emit();
+    }
+
+    /// Moderator only, hide the image and link of the block rooted at `root_id`.
+    /// Deposits of the open flags go back to their flaggers.
+    pub fn hide_block(&mut self, root_id: TokenId, reason_code: u8) {
+        let moderator_id = self.assert_moderator();
+        self.internal_block_owner(&root_id);
+
+        self.block_takedowns.insert(&root_id, &Takedown {
+            moderator_id: moderator_id.clone(),
+            reason_code,
+            hidden_at: U64(env::block_timestamp()),
+        });
+        if let Some(case) = self.moderation_queue.remove(&root_id) {
+            for flag in case.flags {
+                Promise::new(flag.flagger_id).transfer(flag.deposit.0);
+            }
+        }
+
+        BlockHide { moderator_id: &moderator_id, root_id: &root_id, reason_code }.emit();
+    }
+
+    /// Moderator only, close the case of the block rooted at `root_id` without action: the
+    /// block stays as it is, deposits of the open flags go to the contract owner.
+    pub fn dismiss_moderation_case(&mut self, root_id: TokenId) {
+        let moderator_id = self.assert_moderator();
+        let case = self.moderation_queue.remove(&root_id).unwrap_or_else(|| env::panic_str("No moderation case"));
+
+        let forfeited: Balance = case.flags.iter().map(|flag| flag.deposit.0).sum();
+        if forfeited > 0 {
+            Promise::new(self.tokens.owner_id.clone()).transfer(forfeited);
+        }
+
+        ModerationDismiss { moderator_id: &moderator_id, root_id: &root_id }.emit();
+    }
+
+    /// Block owner only, ask the moderators to restore a hidden block.
+    #[payable]
+    pub fn appeal_block(&mut self, root_id: TokenId, message: String) {
+        assert_one_yocto();
+        let owner_id = self.internal_block_owner(&root_id);
+        assert_eq!(owner_id, env::predecessor_account_id(), "No permission to appeal");
+        assert!(self.block_takedowns.get(&root_id).is_some(), "Block is not hidden");
+        assert!(message.len() <= MAX_APPEAL_LEN, "Appeal is too long");
+
+        let mut case = self.moderation_queue.get(&root_id).unwrap_or_default();
+        case.appeal = Some(message);
+        self.moderation_queue.insert(&root_id, &case);
+
+        BlockAppeal { owner_id: &owner_id, root_id: &root_id }.emit();
+    }
+
+    /// Moderator only, show the content of a hidden block again and close its case.
+    pub fn restore_block(&mut self, root_id: TokenId) {
+        let moderator_id = self.assert_moderator();
+        assert!(self.block_takedowns.remove(&root_id).is_some(), "Block is not hidden");
+        self.moderation_queue.remove(&root_id);
+
+        BlockRestore { moderator_id: &moderator_id, root_id: &root_id }.emit();
+    }
+
+    pub fn get_block_takedown(&self, root_id: TokenId) -> Option<Takedown> {
+        self.block_takedowns.get(&root_id)
+    }
+
+    /// Blocks with open flags or appeals. Closing a case moves the last case into its slot, so the
+    /// order is not the order the cases were opened in and pages shift when cases close.
+    pub fn get_moderation_queue(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<ModerationQueueItem> {
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+
+        self.moderation_queue
+            .iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .map(|(root_id, case)| ModerationQueueItem {
+                takedown: self.block_takedowns.get(&root_id),
+                root_id,
+                case,
+            })
+            .collect()
+    }
+}
+
+impl Contract {
+    fn assert_moderator(&self) -> AccountId {
+        let account_id = env::predecessor_account_id();
+        assert!(self.moderators.contains(&account_id), "Only moderators can moderate");
+        account_id
+    }
+
+    pub(crate) fn is_hidden_block(&self, root_id: &TokenId) -> bool {
+        self.block_takedowns.get(root_id).is_some()
+    }
+}
@@ -0,0 +1,188 @@
+use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Balance, Promise};
+
+use crate::events::{OfferAccept, OfferCancel, OfferMake};
+use crate::*;
+
+/// Escrowed offer of `amount` for the block rooted at `root_id`. When `root_id` is an unminted
+/// cell the offer is a wish-list offer, executed as soon as the cell gets minted.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Offer {
+    pub buyer_id: AccountId,
+    pub root_id: TokenId,
+    pub amount: U128,
+    pub expires_at: U64,
+}
+
+impl Offer {
+    pub fn is_expired(&self) -> bool {
+        env::block_timestamp() >= self.expires_at.0
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OfferView {
+    pub offer_id: U64,
+    pub offer: Offer,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Offer `amount` for the block rooted at `root_id` until `expires_at` (nanoseconds). The
+    /// attached deposit must cover the amount and the storage of the offer, the excess is refunded.
+    #[payable]
+    pub fn make_offer(&mut self, root_id: TokenId, amount: U128, expires_at: U64) -> U64 {
+        let initial_storage_usage = env::storage_usage();
+        let buyer_id = env::predecessor_account_id();
+        assert!(amount.0 > 0, "Offer amount must be positive");
+        assert!(expires_at.0 > env::block_timestamp(), "Offer expiry must be in the future");
+        if self.tokens.owner_by_id.get(&root_id).is_some() {
+            assert_ne!(self.internal_block_owner(&root_id), buyer_id, "Cannot make offer on own block");
+        } else {
+            assert!(is_cell_id(&root_id), "Invalid cell id");
+            self.assert_not_reserved(&root_id);
+            assert!(amount.0 >= self.mint_price, "Offer is below mint price");
+        }
+
+        let offer_id = self.next_offer_id;
+        self.next_offer_id += 1;
+        let offer = Offer { buyer_id: buyer_id.clone(), root_id: root_id.clone(), amount, expires_at };
+        self.offers.insert(&offer_id, &offer);
+        let mut offer_ids = self.offers_by_token.get(&root_id).unwrap_or_default();
+        offer_ids.push(offer_id);
+        self.offers_by_token.insert(&root_id, &offer_ids);
+
+        let required_cost = amount.0
+            + Balance::from(env::storage_usage().saturating_sub(initial_storage_usage)) * env::storage_byte_cost();
+        let deposit = env::attached_deposit();
+        assert!(deposit >= required_cost, "Must attach {} yoctoNEAR to cover the offer and storage", required_cost);
+        if deposit > required_cost {
+            Promise::new(buyer_id.clone()).transfer(deposit - required_cost);
+        }
+
+        OfferMake { offer_id: U64(offer_id), buyer_id: &buyer_id, root_id: &root_id, amount, expires_at }.emit();
+        U64(offer_id)
+    }
+
+    /// Sell the block to the offer's buyer, the caller is paid the amount minus the market fee
+    /// and royalties.
+    #[payable]
+    pub fn accept_offer(&mut self, offer_id: U64) {
+        assert_one_yocto();
+        let offer = self.offers.get(&offer_id.0).unwrap_or_else(|| env::panic_str("Offer not found"));
+        let seller_id = env::predecessor_account_id();
+        assert!(!offer.is_expired(), "Offer has expired");
+        assert_eq!(self.internal_block_owner(&offer.root_id), seller_id, "No permission to accept offer");
+
+        self.internal_execute_offer(offer_id.0, &offer, &seller_id);
+    }
+
+    /// Withdraw an offer and get the escrowed amount back. Once expired anyone can cancel it,
+    /// the refund always goes to the buyer.
+    #[payable]
+    pub fn cancel_offer(&mut self, offer_id: U64) {
+        assert_one_yocto();
+        let offer = self.offers.get(&offer_id.0).unwrap_or_else(|| env::panic_str("Offer not found"));
+        assert!(
+            offer.is_expired() || offer.buyer_id == env::predecessor_account_id(),
+            "No permission to cancel offer"
+        );
+
+        let initial_storage_usage = env::storage_usage();
+        self.internal_remove_offer(offer_id.0, &offer.root_id);
+        let storage_released = initial_storage_usage - env::storage_usage();
+        Promise::new(offer.buyer_id.clone())
+            .transfer(offer.amount.0 + Balance::from(storage_released) * env::storage_byte_cost());
+
+        OfferCancel { offer_id, root_id: &offer.root_id }.emit();
+    }
+
+    pub fn get_offer(&self, offer_id: U64) -> Option<Offer> {
+        self.offers.get(&offer_id.0)
+    }
+
+    /// Offers on the block rooted at `root_id` or on the unminted cell `root_id`, highest first.
+    pub fn get_offers_for_block(&self, root_id: TokenId, from_index: Option<U128>, limit: Option<u64>) -> Vec<OfferView> {
+        let mut offers: Vec<OfferView> = self.offers_by_token
+            .get(&root_id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|offer_id| self.offers.get(&offer_id).map(|offer| OfferView { offer_id: U64(offer_id), offer }))
+            .collect();
+        offers.sort_by_key(|view| std::cmp::Reverse(view.offer.amount.0));
+        paginate_offers(offers, from_index, limit)
+    }
+
+    pub fn get_offers_by_buyer(&self, account_id: AccountId, from_index: Option<U128>, limit: Option<u64>) -> Vec<OfferView> {
+        let offers: Vec<OfferView> = self.offers
+            .iter()
+            .filter(|(_, offer)| offer.buyer_id == account_id)
+            .map(|(offer_id, offer)| OfferView { offer_id: U64(offer_id), offer })
+            .collect();
+        paginate_offers(offers, from_index, limit)
+    }
+}
+
+impl Contract {
+    /// Called after `token_id` got minted to `owner_id`: records them as the original minter and
+    /// executes the best live wish-list offer on the cell paying at least the mint price. Returns
+    /// the buyer the cell was sold to.
+    pub(crate) fn internal_on_mint(&mut self, token_id: &TokenId, owner_id: &AccountId) -> Option<AccountId> {
+        self.minters.insert(token_id, owner_id);
+        let best = self.offers_by_token
+            .get(token_id)?
+            .into_iter()
+            .filter_map(|offer_id| self.offers.get(&offer_id).map(|offer| (offer_id, offer)))
+            .filter(|(_, offer)| !offer.is_expired() && &offer.buyer_id != owner_id && offer.amount.0 >= self.mint_price)
+            .max_by_key(|(offer_id, offer)| (offer.amount.0, std::cmp::Reverse(*offer_id)));
+
+        let (offer_id, offer) = best?;
+        self.internal_execute_offer(offer_id, &offer, owner_id);
+        Some(offer.buyer_id)
+    }
+
+    /// Move the block of `offer` from `seller_id` to the buyer and pay the seller. The storage
+    /// of the offer goes back to the buyer.
+    fn internal_execute_offer(&mut self, offer_id: u64, offer: &Offer, seller_id: &AccountId) {
+        let initial_storage_usage = env::storage_usage();
+        self.internal_remove_offer(offer_id, &offer.root_id);
+        let storage_released = initial_storage_usage - env::storage_usage();
+
+        self.internal_transfer_block(&offer.root_id, seller_id, &offer.buyer_id, "offer");
+        let fee = self.internal_pay_sale(&offer.root_id, seller_id, offer.amount.0);
+        Promise::new(offer.buyer_id.clone()).transfer(Balance::from(storage_released) * env::storage_byte_cost());
+
+        OfferAccept {
+            offer_id: U64(offer_id),
+            seller_id,
+            buyer_id: &offer.buyer_id,
+            root_id: &offer.root_id,
+            amount: offer.amount,
+            fee: U128(fee),
+        }
+        .emit();
+    }
+
+    fn internal_remove_offer(&mut self, offer_id: u64, root_id: &TokenId) {
+        self.offers.remove(&offer_id);
+        let mut offer_ids = self.offers_by_token.get(root_id).unwrap_or_default();
+        offer_ids.retain(|id| *id != offer_id);
+        if offer_ids.is_empty() {
+            self.offers_by_token.remove(root_id);
+        } else {
+            self.offers_by_token.insert(root_id, &offer_ids);
+        }
+    }
+}
+
+fn paginate_offers(offers: Vec<OfferView>, from_index: Option<U128>, limit: Option<u64>) -> Vec<OfferView> {
+    let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+    let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+    assert_ne!(limit, 0, "Cannot provide limit of 0.");
+    offers.into_iter().skip(start_index as usize).take(limit).collect()
+}
@@ -0,0 +1,88 @@
+use near_contract_standards::non_fungible_token::{refund_deposit, TokenId};
+use near_sdk::json_types::{Base64VecU8, U64};
+use near_sdk::{env, near_bindgen, AccountId};
+
+use crate::events::{PaintCooldownUpdate, PixelPaint};
+use crate::*;
+
+/// Maximum number of cells painted in one call.
+pub const MAX_PAINT_BATCH: usize = 100;
+/// Bytes per cell in the packed color array, one for each of red, green and blue.
+pub const COLOR_BYTES: usize = 3;
+
+/// Default time an account waits between two paints of unminted cells, 5 minutes.
+pub const DEFAULT_PAINT_COOLDOWN: u64 = 5 * 60 * 1_000_000_000;
+
+/// Split a `0xRRGGBB` color into its bytes.
+pub fn rgb_to_bytes(rgb: u32) -> [u8; 3] {
+    assert!(rgb <= 0xFFFFFF, "Invalid color");
+    [(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8]
+}
+
+pub fn bytes_to_rgb(bytes: [u8; 3]) -> u32 {
+    ((bytes[0] as u32) << 16) | ((bytes[1] as u32) << 8) | bytes[2] as u32
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Paint cells, each with a `0xRRGGBB` color. Minted cells can only be painted by their owner,
+    /// unminted cells are a public canvas: anyone can paint one of them per cooldown period.
+    /// The attached deposit must cover the storage used, the excess is refunded.
+    #[payable]
+    pub fn paint_cells(&mut self, cells: Vec<(TokenId, u32)>) {
+        assert!(!cells.is_empty() && cells.len() <= MAX_PAINT_BATCH, "Paint between 1 and {} cells", MAX_PAINT_BATCH);
+        let initial_storage_usage = env::storage_usage();
+        let owner_id = env::predecessor_account_id();
+
+        let mut public_painted = false;
+        for (token_id, rgb) in &cells {
+            if self.tokens.owner_by_id.get(token_id).is_some() {
+                assert_eq!(self.internal_cell_owner(token_id), owner_id, "No permission to paint");
+            } else {
+                assert!(is_cell_id(token_id), "Invalid cell id");
+                assert!(!public_painted, "Only one unminted cell can be painted per cooldown");
+                assert_eq!(self.get_paint_cooldown(owner_id.clone()).0, 0, "Paint cooldown not over");
+                self.last_public_paint.insert(&owner_id, &env::block_timestamp());
+                public_painted = true;
+            }
+            self.cell_colors.insert(token_id, &rgb_to_bytes(*rgb));
+        }
+
+        let token_ids: Vec<&str> = cells.iter().map(|(token_id, _)| token_id.as_str()).collect();
+        let colors: Vec<u32> = cells.iter().map(|(_, rgb)| *rgb).collect();
+        PixelPaint { painter_id: &owner_id, token_ids: &token_ids, colors: &colors }.emit();
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage));
+    }
+
+    /// Time left, in nanoseconds, before `account_id` can paint an unminted cell again.
+    pub fn get_paint_cooldown(&self, account_id: AccountId) -> U64 {
+        let ready_at = match self.last_public_paint.get(&account_id) {
+            Some(painted_at) => painted_at + self.paint_cooldown,
+            None => 0
+        };
+        U64(ready_at.saturating_sub(env::block_timestamp()))
+    }
+
+    /// Owner only, set the time in nanoseconds between two paints of unminted cells by one account.
+    pub fn set_paint_cooldown(&mut self, paint_cooldown: U64) {
+        assert_eq!(env::predecessor_account_id(), self.tokens.owner_id, "Unauthorized");
+        PaintCooldownUpdate { old_cooldown: U64(self.paint_cooldown), new_cooldown: paint_cooldown }.emit();
+        self.paint_cooldown = paint_cooldown.0;
+    }
+
+    pub fn get_cell_color(&self, token_id: TokenId) -> Option<u32> {
+        self.cell_colors.get(&token_id).map(bytes_to_rgb)
+    }
+
+    /// Colors of the `width` x `height` area starting at `token_id`, packed as RGB bytes in the
+    /// order of `iterate_token_area`. Unpainted cells are `0, 0, 0`.
+    pub fn get_region_colors(&self, token_id: TokenId, width: u8, height: u8) -> Base64VecU8 {
+        let mut colors: Vec<u8> = Vec::with_capacity(width as usize * height as usize * COLOR_BYTES);
+        iterate_token_area(token_id, width, height, |sub_token_id| -> bool {
+            colors.extend_from_slice(&self.cell_colors.get(&sub_token_id).unwrap_or_default());
+            true
+        });
+        colors.into()
+    }
+}
@@ -0,0 +1,349 @@
+use near_contract_standards::non_fungible_token::{refund_deposit, TokenId};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Balance, Promise};
+
+use crate::events::{BlockRent, BookingCancel, RentalTermsSet};
+use crate::market::FEE_DENOMINATOR;
+use crate::*;
+
+/// One day in nanoseconds, the unit rentals are priced in.
+pub const DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+/// Bookings can start at most a year ahead.
+pub const MAX_BOOKING_AHEAD: u64 = 365 * DAY;
+/// Upcoming bookings kept per block.
+pub const MAX_BOOKINGS: usize = 50;
+/// Cancelling at least a week before the start refunds the whole prepayment.
+pub const CANCEL_NOTICE: u64 = 7 * DAY;
+/// Share of the prepayment refunded on a late cancellation, in basis points.
+pub const LATE_CANCEL_REFUND: u128 = 5_000;
+
+/// Terms under which the owner rents out the billboard of a block.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RentalTerms {
+    pub price_per_day: U128,
+    pub min_days: u32,
+    pub max_days: u32,
+}
+
+/// A prepaid booking of a block from `start_at` to `end_at`. During that time the renter controls
+/// the image and link of the block, their content is shown over the owner's.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Rental {
+    pub renter_id: AccountId,
+    /// Owner when the booking was made, who receives the rent.
+    pub owner_id: AccountId,
+    pub price: U128,
+    pub start_at: U64,
+    pub end_at: U64,
+    pub image: Option<BlockImage>,
+    pub link: Option<BlockLink>,
+    /// Whether the rent was credited to the owner, which happens once the booking starts.
+    pub paid_out: bool,
+}
+
+impl Rental {
+    pub fn is_active(&self) -> bool {
+        self.covers(env::block_timestamp())
+    }
+
+    pub fn covers(&self, timestamp: u64) -> bool {
+        self.start_at.0 <= timestamp && timestamp < self.end_at.0
+    }
+}
+
+/// What a block shows at a given time.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlockContent {
+    /// Renter whose booking covers the time, `None` when the owner's content is shown.
+    pub renter_id: Option<AccountId>,
+    pub image: Option<BlockImage>,
+    pub link: Option<BlockLink>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Offer the block rooted at `root_id` for rent, replacing previous terms. The terms are
+    /// cleared when the block changes hands. The attached deposit must cover the storage used,
+    /// the excess is refunded.
+    #[payable]
+    pub fn set_rental_terms(&mut self, root_id: TokenId, terms: RentalTerms) {
+        let initial_storage_usage = env::storage_usage();
+        let owner_id = self.internal_block_owner(&root_id);
+        assert_eq!(owner_id, env::predecessor_account_id(), "No permission to rent out");
+        assert!(terms.price_per_day.0 > 0, "Price must be positive");
+        assert!(terms.min_days > 0 && terms.min_days <= terms.max_days, "Invalid rental duration");
+
+        self.rental_terms.insert(&root_id, &terms);
+        RentalTermsSet { owner_id: &owner_id, root_id: &root_id, terms: Some(&terms) }.emit();
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage));
+    }
+
+    /// Stop renting out the block rooted at `root_id`, existing bookings are not affected.
+    #[payable]
+    pub fn remove_rental_terms(&mut self, root_id: TokenId) {
+        assert_one_yocto();
+        let owner_id = self.internal_block_owner(&root_id);
+        assert_eq!(owner_id, env::predecessor_account_id(), "No permission to rent out");
+        if self.rental_terms.remove(&root_id).is_some() {
+            RentalTermsSet { owner_id: &owner_id, root_id: &root_id, terms: None }.emit();
+        }
+    }
+
+    /// Rent the block rooted at `root_id` for `days` days from now, paying upfront.
+    /// The excess of the attached deposit is refunded.
+    #[payable]
+    pub fn rent_block(&mut self, root_id: TokenId, days: u32) {
+        self.book_block(root_id, U64(env::block_timestamp()), days);
+    }
+
+    /// Book the block rooted at `root_id` for `days` days from `start_at` (nanoseconds), paying
+    /// upfront. The booking must not overlap another one. The excess of the attached deposit is
+    /// refunded.
+    #[payable]
+    pub fn book_block(&mut self, root_id: TokenId, start_at: U64, days: u32) {
+        let terms = self.rental_terms.get(&root_id).unwrap_or_else(|| env::panic_str("Block is not for rent"));
+        let renter_id = env::predecessor_account_id();
+        let owner_id = self.internal_block_owner(&root_id);
+        assert_ne!(renter_id, owner_id, "Owner cannot rent own block");
+        assert!(days >= terms.min_days && days <= terms.max_days, "Rental duration out of terms");
+        let now = env::block_timestamp();
+        assert!(start_at.0 >= now, "Booking must not start in the past");
+        assert!(start_at.0 - now <= MAX_BOOKING_AHEAD, "Booking is too far ahead");
+
+        let initial_storage_usage = env::storage_usage();
+        let mut bookings = self.internal_settle_bookings(&root_id);
+        let end_at = U64(start_at.0 + days as u64 * DAY);
+        assert!(
+            bookings.iter().all(|booking| end_at.0 <= booking.start_at.0 || booking.end_at.0 <= start_at.0),
+            "Block is already rented"
+        );
+        assert!(bookings.len() < MAX_BOOKINGS, "Too many bookings");
+
+        let price = terms.price_per_day.0 * days as u128;
+        let index = bookings.iter().position(|booking| booking.start_at.0 > start_at.0).unwrap_or(bookings.len());
+        bookings.insert(index, Rental {
+            renter_id: renter_id.clone(),
+            owner_id,
+            price: U128(price),
+            start_at,
+            end_at,
+            image: None,
+            link: None,
+            paid_out: false,
+        });
+        self.rentals.insert(&root_id, &bookings);
+        self.internal_settle_bookings(&root_id);
+
+        let required_cost = price
+            + Balance::from(env::storage_usage().saturating_sub(initial_storage_usage)) * env::storage_byte_cost();
+        let deposit = env::attached_deposit();
+        assert!(deposit >= required_cost, "Must attach {} yoctoNEAR to cover the rent and storage", required_cost);
+        if deposit > required_cost {
+            Promise::new(renter_id.clone()).transfer(deposit - required_cost);
+        }
+
+        BlockRent { renter_id: &renter_id, root_id: &root_id, price: U128(price), start_at, end_at }.emit();
+    }
+
+    /// Cancel the caller's booking of the block rooted at `root_id` starting at `start_at`, before
+    /// it starts. The prepayment is refunded in full with at least `CANCEL_NOTICE` notice, half of
+    /// it otherwise; the rest goes to the owner.
+    #[payable]
+    pub fn cancel_booking(&mut self, root_id: TokenId, start_at: U64) {
+        assert_one_yocto();
+        let renter_id = env::predecessor_account_id();
+        let mut bookings = self.internal_settle_bookings(&root_id);
+        let index = bookings.iter()
+            .position(|booking| booking.start_at == start_at && booking.renter_id == renter_id)
+            .unwrap_or_else(|| env::panic_str("Booking not found"));
+        let now = env::block_timestamp();
+        assert!(now < start_at.0, "Booking has started");
+
+        let booking = bookings.remove(index);
+        self.rentals.insert(&root_id, &bookings);
+
+        let price = booking.price.0;
+        let refund = if start_at.0 - now >= CANCEL_NOTICE { price } else { price * LATE_CANCEL_REFUND / FEE_DENOMINATOR };
+        self.internal_credit_rental_income(&booking.owner_id, price - refund);
+        if refund > 0 {
+            Promise::new(renter_id.clone()).transfer(refund);
+        }
+        BookingCancel { renter_id: &renter_id, root_id: &root_id, start_at, refund: U128(refund) }.emit();
+    }
+
+    /// Credit the rent of the started bookings of the block rooted at `root_id` to their owners
+    /// and forget the finished ones. Callable by anyone.
+    pub fn settle_bookings(&mut self, root_id: TokenId) {
+        self.internal_settle_bookings(&root_id);
+    }
+
+    /// Send the rent accrued by the caller to them.
+    #[payable]
+    pub fn withdraw_rental_income(&mut self) -> U128 {
+        assert_one_yocto();
+        let owner_id = env::predecessor_account_id();
+        let income = self.rental_income.remove(&owner_id).unwrap_or(0);
+        if income > 0 {
+            Promise::new(owner_id).transfer(income);
+        }
+        U128(income)
+    }
+
+    pub fn get_rental_income(&self, account_id: AccountId) -> U128 {
+        U128(self.rental_income.get(&account_id).unwrap_or(0))
+    }
+
+    pub fn get_rental_terms(&self, root_id: TokenId) -> Option<RentalTerms> {
+        self.rental_terms.get(&root_id)
+    }
+
+    /// The running rental of the block rooted at `root_id`.
+    pub fn get_rental(&self, root_id: TokenId) -> Option<Rental> {
+        self.internal_active_rental(&root_id)
+    }
+
+    /// Running and upcoming bookings of the block rooted at `root_id`, earliest first.
+    pub fn get_bookings(&self, root_id: TokenId, from_index: Option<U128>, limit: Option<u64>) -> Vec<Rental> {
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        let limit = limit.map(|v| v as usize).unwrap_or(usize::MAX);
+        assert_ne!(limit, 0, "Cannot provide limit of 0.");
+        let now = env::block_timestamp();
+        self.rentals.get(&root_id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|booking| booking.end_at.0 > now)
+            .skip(start_index as usize)
+            .take(limit)
+            .collect()
+    }
+
+    /// Content shown by the block rooted at `root_id` at `timestamp` (nanoseconds): the renter's
+    /// when a booking covers it, the current owner content otherwise. Empty while the block is
+    /// hidden by a moderator.
+    pub fn get_block_content_at(&self, root_id: TokenId, timestamp: U64) -> BlockContent {
+        if self.is_hidden_block(&root_id) {
+            return BlockContent { renter_id: None, image: None, link: None };
+        }
+        match self.internal_booking_at(&root_id, timestamp.0) {
+            Some(booking) => BlockContent {
+                renter_id: Some(booking.renter_id),
+                image: booking.image.or_else(|| self.block_images.get(&root_id)),
+                link: booking.link.or_else(|| self.block_links.get(&root_id)),
+            },
+            None => BlockContent {
+                renter_id: None,
+                image: self.block_images.get(&root_id),
+                link: self.block_links.get(&root_id),
+            },
+        }
+    }
+
+    /// Set the image and link shown during the caller's booking of the block rooted at `root_id`
+    /// starting at `start_at`, ahead of time. The attached deposit must cover the storage used,
+    /// the excess is refunded.
+    #[payable]
+    pub fn set_booking_content(&mut self, root_id: TokenId, start_at: U64, image: Option<BlockImage>, link: Option<BlockLink>) {
+        let initial_storage_usage = env::storage_usage();
+        let renter_id = env::predecessor_account_id();
+        if let Some(image) = &image {
+            image.assert_valid();
+        }
+        if let Some(link) = &link {
+            link.assert_valid();
+        }
+
+        let mut bookings = self.rentals.get(&root_id).unwrap_or_default();
+        let booking = bookings.iter_mut()
+            .find(|booking| booking.start_at == start_at && booking.renter_id == renter_id)
+            .unwrap_or_else(|| env::panic_str("Booking not found"));
+        assert!(booking.end_at.0 > env::block_timestamp(), "Booking has ended");
+        booking.image = image;
+        booking.link = link;
+        self.rentals.insert(&root_id, &bookings);
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage));
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_active_rental(&self, root_id: &TokenId) -> Option<Rental> {
+        self.internal_booking_at(root_id, env::block_timestamp())
+    }
+
+    fn internal_booking_at(&self, root_id: &TokenId, timestamp: u64) -> Option<Rental> {
+        self.rentals.get(root_id)?.into_iter().find(|booking| booking.covers(timestamp))
+    }
+
+    /// Whether the block rooted at `root_id` has a running or upcoming booking.
+    pub(crate) fn is_booked(&self, root_id: &TokenId) -> bool {
+        let now = env::block_timestamp();
+        self.rentals.get(root_id).unwrap_or_default().iter().any(|booking| booking.end_at.0 > now)
+    }
+
+    /// Replace the running booking of the block rooted at `root_id`.
+    pub(crate) fn internal_update_active_rental(&mut self, root_id: &TokenId, rental: Rental) {
+        let mut bookings = self.rentals.get(root_id).unwrap_or_default();
+        if let Some(booking) = bookings.iter_mut().find(|booking| booking.start_at == rental.start_at) {
+            *booking = rental;
+        }
+        self.rentals.insert(root_id, &bookings);
+    }
+
+    /// Credit the rent of started bookings to their owners and drop finished bookings.
+    /// Returns the remaining bookings.
+    pub(crate) fn internal_settle_bookings(&mut self, root_id: &TokenId) -> Vec<Rental> {
+        let bookings = match self.rentals.get(root_id) {
+            Some(bookings) => bookings,
+            None => return Vec::new(),
+        };
+        let now = env::block_timestamp();
+        let mut remaining: Vec<Rental> = Vec::new();
+        for mut booking in bookings {
+            if !booking.paid_out && booking.start_at.0 <= now {
+                self.internal_credit_rental_income(&booking.owner_id, booking.price.0);
+                booking.paid_out = true;
+            }
+            if booking.end_at.0 > now {
+                remaining.push(booking);
+            }
+        }
+        if remaining.is_empty() {
+            self.rentals.remove(root_id);
+        } else {
+            self.rentals.insert(root_id, &remaining);
+        }
+        remaining
+    }
+
+    fn internal_credit_rental_income(&mut self, owner_id: &AccountId, amount: Balance) {
+        if amount > 0 {
+            let income = self.rental_income.get(owner_id).unwrap_or(0);
+            self.rental_income.insert(owner_id, &(income + amount));
+        }
+    }
+
+    /// Whether `account_id` currently rents the block rooted at `root_id`.
+    pub(crate) fn is_active_renter(&self, root_id: &TokenId, account_id: &AccountId) -> bool {
+        matches!(self.internal_active_rental(root_id), Some(rental) if &rental.renter_id == account_id)
+    }
+
+    /// Image shown on the block rooted at `root_id`: the renter's while rented, the owner's otherwise.
+    pub(crate) fn internal_block_image(&self, root_id: &TokenId) -> Option<BlockImage> {
+        self.internal_active_rental(root_id)
+            .and_then(|rental| rental.image)
+            .or_else(|| self.block_images.get(root_id))
+    }
+
+    /// Link of the block rooted at `root_id`: the renter's while rented, the owner's otherwise.
+    pub(crate) fn internal_block_link(&self, root_id: &TokenId) -> Option<BlockLink> {
+        self.internal_active_rental(root_id)
+            .and_then(|rental| rental.link)
+            .or_else(|| self.block_links.get(root_id))
+    }
+}
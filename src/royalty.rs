@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Balance};
+
+use crate::events::RoyaltyUpdate;
+use crate::market::FEE_DENOMINATOR;
+use crate::*;
+
+/// Default royalty of the original minters, 2.5%.
+pub const DEFAULT_MINTER_ROYALTY: u16 = 250;
+/// Royalties can take at most half of a sale.
+pub const MAX_ROYALTY: u16 = 5_000;
+
+/// NEP-199 payout, the amount each account receives out of a sale.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Payout {
+    pub payout: HashMap<AccountId, U128>,
+}
+
+/// Royalties in basis points: `minter_bps` is shared by the original minters of the sold cells
+/// pro rata to the number of cells they minted, `treasury_bps` goes to `treasury_id`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoyaltyConfig {
+    pub treasury_id: AccountId,
+    pub minter_bps: u16,
+    pub treasury_bps: u16,
+}
+
+impl RoyaltyConfig {
+    pub fn assert_valid(&self) {
+        assert!(self.minter_bps as u32 + self.treasury_bps as u32 <= MAX_ROYALTY as u32, "Royalty exceeds {}", MAX_ROYALTY);
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Owner only, replace the collection-level royalty.
+    pub fn set_royalty_config(&mut self, config: RoyaltyConfig) {
+        assert_eq!(env::predecessor_account_id(), self.tokens.owner_id, "Unauthorized");
+        config.assert_valid();
+        self.royalty = config;
+        RoyaltyUpdate { root_id: None, config: Some(&self.royalty) }.emit();
+    }
+
+    /// Owner only, override the royalty of the block rooted at `root_id`, `None` falls back to the
+    /// collection-level royalty.
+    pub fn set_block_royalty_config(&mut self, root_id: TokenId, config: Option<RoyaltyConfig>) {
+        assert_eq!(env::predecessor_account_id(), self.tokens.owner_id, "Unauthorized");
+        match &config {
+            Some(config) => {
+                config.assert_valid();
+                self.block_royalties.insert(&root_id, config);
+            }
+            None => {
+                self.block_royalties.remove(&root_id);
+            }
+        }
+        RoyaltyUpdate { root_id: Some(&root_id), config: config.as_ref() }.emit();
+    }
+
+    pub fn get_royalty_config(&self) -> RoyaltyConfig {
+        self.royalty.clone()
+    }
+
+    /// Royalty applied to sales of the block rooted at `root_id`.
+    pub fn get_block_royalty_config(&self, root_id: TokenId) -> RoyaltyConfig {
+        self.block_royalties.get(&root_id).unwrap_or_else(|| self.royalty.clone())
+    }
+
+    pub fn get_minter(&self, token_id: TokenId) -> Option<AccountId> {
+        self.minters.get(&token_id)
+    }
+
+    /// Split of `balance` between royalties and the owner if `token_id` is sold. A block token
+    /// pays royalties to the minters of every cell of the block. A cell of a merged block cannot be
+    /// sold on its own, the block must be locked and sold through its block token.
+    pub fn nft_payout(&self, token_id: TokenId, balance: U128, max_len_payout: Option<u32>) -> Payout {
+        let owner_id = expect_token_found(self.tokens.owner_by_id.get(&token_id));
+        let (root_id, cells) = match block_root_id(&token_id) {
+            Some(root_id) => {
+                let cells = self.get_block_cells(root_id.clone());
+                (root_id, cells)
+            }
+            None => {
+                assert!(
+                    !self.is_covered_token(&token_id) && self.get_block_cells(token_id.clone()).len() == 1,
+                    "Lock the merged block to sell it"
+                );
+                (token_id.clone(), vec![token_id])
+            }
+        };
+
+        let payout = self.internal_payout(&root_id, &cells, &owner_id, balance.0);
+        if let Some(max_len_payout) = max_len_payout {
+            assert!(payout.len() <= max_len_payout as usize, "Payout exceeds max_len_payout");
+        }
+        Payout { payout: payout.into_iter().map(|(account_id, amount)| (account_id, U128(amount))).collect() }
+    }
+
+    /// Transfer `token_id` like `nft_transfer` and return how the marketplace must split `balance`.
+    #[payable]
+    pub fn nft_transfer_payout(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        balance: U128,
+        max_len_payout: Option<u32>,
+    ) -> Payout {
+        assert_one_yocto();
+        let payout = self.nft_payout(token_id.clone(), balance, max_len_payout);
+        self.nft_transfer(receiver_id, token_id, approval_id, memo);
+        payout
+    }
+}
+
+impl Contract {
+    /// Amounts owed out of `balance` when `owner_id` sells `cells` of the block rooted at `root_id`.
+    /// The owner receives what is left once royalties are paid, rounding included.
+    pub(crate) fn internal_payout(
+        &self,
+        root_id: &TokenId,
+        cells: &[TokenId],
+        owner_id: &AccountId,
+        balance: Balance,
+    ) -> HashMap<AccountId, Balance> {
+        let config = self.get_block_royalty_config(root_id.clone());
+        let mut payout: HashMap<AccountId, Balance> = HashMap::new();
+
+        let treasury_amount = balance * config.treasury_bps as u128 / FEE_DENOMINATOR;
+        *payout.entry(config.treasury_id).or_default() += treasury_amount;
+
+        let minter_amount = balance * config.minter_bps as u128 / FEE_DENOMINATOR;
+        let cell_amount = minter_amount / cells.len().max(1) as u128;
+        for cell in cells {
+            if let Some(minter_id) = self.minters.get(cell) {
+                *payout.entry(minter_id).or_default() += cell_amount;
+            }
+        }
+
+        payout.remove(owner_id);
+        payout.retain(|_, amount| *amount > 0);
+        let royalties: Balance = payout.values().sum();
+        payout.insert(owner_id.clone(), balance - royalties);
+        payout
+    }
+}
@@ -0,0 +1,178 @@
+use near_contract_standards::non_fungible_token::{refund_deposit, TokenId};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Balance};
+
+use crate::events::{BlockStake, BlockUnstake, RewardClaim, RewardRateUpdate};
+use crate::rental::DAY;
+use crate::*;
+
+/// Rewards are emitted per epoch.
+pub const EPOCH: u64 = DAY;
+/// Default reward points per plain cell per epoch.
+pub const DEFAULT_REWARD_RATE: u128 = 100;
+/// Weight of a plain cell, weights are in basis points of it.
+pub const BASE_WEIGHT: u128 = 10_000;
+/// Cells within this many cells of the origin on both axes count double.
+pub const CENTER_RADIUS: i128 = 50;
+pub const CENTER_BONUS: u128 = 10_000;
+/// Merged blocks earn 20% more per cell.
+pub const MERGE_BONUS: u128 = 2_000;
+/// Scale of the reward accumulator.
+const ACC_PRECISION: u128 = 1_000_000_000_000;
+
+/// Global reward accumulator: reward points earned by one unit of weight since staking started,
+/// scaled by `ACC_PRECISION`. Claiming only compares a stake against it, so it is O(1).
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct RewardAccumulator {
+    pub acc_reward_per_weight: u128,
+    pub updated_at: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Stake {
+    pub owner_id: AccountId,
+    pub weight: U128,
+    /// Accumulator value rewards were last claimed at.
+    pub acc_at: U128,
+    pub staked_at: U64,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakeView {
+    pub stake: Stake,
+    pub pending_rewards: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Stake the block rooted at `root_id`. Staked land earns reward points every epoch and
+    /// cannot be transferred, merged or unmerged until unstaked. A listing of the block is
+    /// cancelled. The attached deposit must cover the storage used, the excess is refunded.
+    #[payable]
+    pub fn stake_block(&mut self, root_id: TokenId) {
+        assert!(!is_block_token(&root_id), "Stake the block by its root id");
+        let initial_storage_usage = env::storage_usage();
+        let owner_id = self.internal_block_owner(&root_id);
+        assert_eq!(owner_id, env::predecessor_account_id(), "No permission to stake");
+        self.assert_not_staked(&root_id);
+        self.assert_not_on_auction(&root_id);
+
+        self.internal_update_rewards();
+        let weight = self.internal_block_weight(&root_id);
+        self.internal_remove_listing(&root_id);
+        self.stakes.insert(&root_id, &Stake {
+            owner_id: owner_id.clone(),
+            weight: U128(weight),
+            acc_at: U128(self.reward_accumulator.acc_reward_per_weight),
+            staked_at: U64(env::block_timestamp()),
+        });
+        BlockStake { owner_id: &owner_id, root_id: &root_id, weight: U128(weight) }.emit();
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage));
+    }
+
+    /// Claim the pending rewards of the block rooted at `root_id` and unstake it.
+    #[payable]
+    pub fn unstake_block(&mut self, root_id: TokenId) {
+        assert_one_yocto();
+        let stake = self.stakes.get(&root_id).unwrap_or_else(|| env::panic_str("Block is not staked"));
+        assert_eq!(stake.owner_id, env::predecessor_account_id(), "No permission to unstake");
+        self.internal_unstake(&root_id);
+    }
+
+    /// Credit the pending rewards of the staked block rooted at `root_id` to its owner.
+    pub fn claim_rewards(&mut self, root_id: TokenId) -> U128 {
+        let stake = self.stakes.get(&root_id).unwrap_or_else(|| env::panic_str("Block is not staked"));
+        assert_eq!(stake.owner_id, env::predecessor_account_id(), "No permission to claim");
+        U128(self.internal_claim_rewards(&root_id, stake))
+    }
+
+    /// Owner only, set the reward points a plain cell earns per epoch. Rewards earned so far
+    /// are kept at the previous rate.
+    pub fn set_reward_rate(&mut self, reward_rate: U128) {
+        assert_eq!(env::predecessor_account_id(), self.tokens.owner_id, "Unauthorized");
+        self.internal_update_rewards();
+        RewardRateUpdate { old_rate: U128(self.reward_rate), new_rate: reward_rate }.emit();
+        self.reward_rate = reward_rate.0;
+    }
+
+    pub fn get_reward_rate(&self) -> U128 {
+        U128(self.reward_rate)
+    }
+
+    pub fn get_reward_points(&self, account_id: AccountId) -> U128 {
+        U128(self.reward_points.get(&account_id).unwrap_or(0))
+    }
+
+    pub fn get_stake(&self, root_id: TokenId) -> Option<StakeView> {
+        let stake = self.stakes.get(&root_id)?;
+        let pending_rewards = U128(pending_rewards(&stake, self.internal_acc_reward_per_weight()));
+        Some(StakeView { stake, pending_rewards })
+    }
+}
+
+impl Contract {
+    /// Staked land cannot change hands nor shape.
+    pub(crate) fn assert_not_staked(&self, root_id: &TokenId) {
+        assert!(self.stakes.get(root_id).is_none(), "Block is staked");
+    }
+
+    /// Claim the rewards of the block rooted at `root_id` and unstake it, if staked.
+    pub(crate) fn internal_unstake(&mut self, root_id: &TokenId) {
+        if let Some(stake) = self.stakes.get(root_id) {
+            self.internal_claim_rewards(root_id, stake.clone());
+            self.stakes.remove(root_id);
+            BlockUnstake { owner_id: &stake.owner_id, root_id }.emit();
+        }
+    }
+
+    fn internal_claim_rewards(&mut self, root_id: &TokenId, mut stake: Stake) -> Balance {
+        self.internal_update_rewards();
+        let acc = self.reward_accumulator.acc_reward_per_weight;
+        let rewards = pending_rewards(&stake, acc);
+        stake.acc_at = U128(acc);
+        self.stakes.insert(root_id, &stake);
+        if rewards > 0 {
+            let points = self.reward_points.get(&stake.owner_id).unwrap_or(0);
+            self.reward_points.insert(&stake.owner_id, &(points + rewards));
+            RewardClaim { owner_id: &stake.owner_id, root_id, amount: U128(rewards) }.emit();
+        }
+        rewards
+    }
+
+    /// Accumulator value at the current block time.
+    fn internal_acc_reward_per_weight(&self) -> u128 {
+        let accumulator = &self.reward_accumulator;
+        let elapsed = env::block_timestamp().saturating_sub(accumulator.updated_at) as u128;
+        accumulator.acc_reward_per_weight + self.reward_rate * elapsed * ACC_PRECISION / EPOCH as u128
+    }
+
+    fn internal_update_rewards(&mut self) {
+        self.reward_accumulator = RewardAccumulator {
+            acc_reward_per_weight: self.internal_acc_reward_per_weight(),
+            updated_at: env::block_timestamp(),
+        };
+    }
+
+    /// Reward weight of the block rooted at `root_id`: `BASE_WEIGHT` per cell, `CENTER_BONUS` more
+    /// for cells near the center, all raised by `MERGE_BONUS` for merged blocks.
+    fn internal_block_weight(&self, root_id: &TokenId) -> u128 {
+        let cells = self.get_block_cells(root_id.clone());
+        let weight: u128 = cells.iter()
+            .map(|cell| {
+                let (x, y) = get_coord(cell.clone());
+                if x.abs() <= CENTER_RADIUS && y.abs() <= CENTER_RADIUS { BASE_WEIGHT + CENTER_BONUS } else { BASE_WEIGHT }
+            })
+            .sum();
+        if cells.len() > 1 { weight * (BASE_WEIGHT + MERGE_BONUS) / BASE_WEIGHT } else { weight }
+    }
+}
+
+/// Reward points earned by `stake` up to the accumulator value `acc`.
+fn pending_rewards(stake: &Stake, acc: u128) -> Balance {
+    stake.weight.0 * (acc - stake.acc_at.0) / (BASE_WEIGHT * ACC_PRECISION)
+}
@@ -0,0 +1,59 @@
+use near_contract_standards::non_fungible_token::metadata::TokenMetadata;
+use near_contract_standards::non_fungible_token::Token;
+use near_sdk::serde_json::json;
+
+use crate::*;
+
+impl Contract {
+    /// Fill in `title`, `media` and `extra` of `token` at read time from the merge and image
+    /// state of the block it belongs to, so wallets show the billboard rather than the metadata
+    /// stored at mint.
+    pub(crate) fn internal_token_view(&self, mut token: Token) -> Token {
+        let cell_id = block_root_id(&token.token_id).unwrap_or_else(|| token.token_id.clone());
+        let root_id = self.token_merged.get(&cell_id).unwrap_or_else(|| cell_id.clone());
+        let rects = self.get_token_shape(root_id.clone()).rects;
+        let (width, height) = shape_size(&rects);
+        let (x, y) = get_coord(cell_id.clone());
+        let image = if self.is_hidden_block(&root_id) { None } else { self.internal_block_image(&root_id) };
+
+        let mut metadata = token.metadata.unwrap_or_else(empty_token_metadata);
+        metadata.title = Some(if cell_id == root_id && width * height > 1 {
+            format!("Block ({}, {}) {}x{}", x, y, width, height)
+        } else {
+            format!("Pixel ({}, {})", x, y)
+        });
+        if let Some(image) = &image {
+            metadata.media = Some(image.cid.clone());
+            metadata.media_hash = Some(image.hash.clone());
+        }
+        metadata.extra = Some(json!({
+            "x": x as i64,
+            "y": y as i64,
+            "root": root_id,
+            "width": width,
+            "height": height,
+            "rects": rects,
+            "cid": image.map(|image| image.cid),
+        }).to_string());
+
+        token.metadata = Some(metadata);
+        token
+    }
+}
+
+fn empty_token_metadata() -> TokenMetadata {
+    TokenMetadata {
+        title: None,
+        description: None,
+        media: None,
+        media_hash: None,
+        copies: None,
+        issued_at: None,
+        expires_at: None,
+        starts_at: None,
+        updated_at: None,
+        extra: None,
+        reference: None,
+        reference_hash: None,
+    }
+}
@@ -1,6 +1,7 @@
 use near_sdk::env;
 use near_contract_standards::non_fungible_token::TokenId;
 use std::cmp::{max};
+use std::collections::HashSet;
 
 pub const _WORLD_SIZE: u128 = 100;
 
@@ -60,6 +61,15 @@ pub fn get_token_id(x: i128, y: i128) -> TokenId {
     index.to_string()
 }
 
+/// Whether `token_id` is the canonical id of a map cell.
+pub fn is_cell_id(token_id: &str) -> bool {
+    if token_id.parse::<u64>().is_err() {
+        return false;
+    }
+    let (x, y) = get_coord(token_id.to_string());
+    get_token_id(x, y) == token_id
+}
+
 pub fn expect_token_found<T>(option: Option<T>) -> T {
     option.unwrap_or_else(|| env::panic_str("Token not found"))
 }
@@ -84,6 +94,28 @@ pub fn iterate_token_area<F: FnMut(TokenId) -> bool>(token_id: TokenId, width: u
     }
 }
 
+/// Check that `cells` form a single 4-connected area.
+pub fn is_connected(cells: &HashSet<(i128, i128)>) -> bool {
+    let start = match cells.iter().next() {
+        Some(cell) => *cell,
+        None => return false
+    };
+
+    let mut visited: HashSet<(i128, i128)> = HashSet::new();
+    let mut stack = vec![start];
+    visited.insert(start);
+
+    while let Some((x, y)) = stack.pop() {
+        for neighbor in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+            if cells.contains(&neighbor) && visited.insert(neighbor) {
+                stack.push(neighbor);
+            }
+        }
+    }
+
+    visited.len() == cells.len()
+}
+
 #[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests {
     use super::*;
@@ -128,4 +160,24 @@ mod tests {
         let token_ids: Vec<&str> = token_ids_vec.iter().map(|token_id| token_id.as_str()).collect();
         assert_eq!(token_ids, rs);
     }
+
+    #[test]
+    fn test_is_cell_id() {
+        assert!(is_cell_id("0"));
+        assert!(is_cell_id("42"));
+        assert!(!is_cell_id("042"));
+        assert!(!is_cell_id("-1"));
+        assert!(!is_cell_id("block:7"));
+    }
+
+    #[test]
+    fn test_is_connected() {
+        // L shape
+        let cells: HashSet<(i128, i128)> = [(0, 0), (0, 1), (0, 2), (1, 2)].into_iter().collect();
+        assert!(is_connected(&cells));
+
+        // diagonal cells are not 4-connected
+        let cells: HashSet<(i128, i128)> = [(0, 0), (1, 1)].into_iter().collect();
+        assert!(!is_connected(&cells));
+    }
 }
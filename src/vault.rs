@@ -0,0 +1,314 @@
+use near_contract_standards::fungible_token::metadata::{FungibleTokenMetadata, FT_METADATA_SPEC};
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::LookupMap;
+use near_sdk::json_types::{Base64VecU8, U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::{self, json};
+use near_sdk::{
+    assert_one_yocto, env, ext_contract, is_promise_success, near_bindgen, AccountId, Balance, Gas, Promise,
+    PromiseOrValue,
+};
+
+use crate::events::{VaultCreate, VaultImagePropose, VaultRedeem, VaultVote};
+use crate::rental::DAY;
+use crate::*;
+
+const GAS_FOR_VAULT_TOKEN_NEW: Gas = Gas(20_000_000_000_000);
+const GAS_FOR_RESOLVE_VAULT_TOKEN: Gas = Gas(30_000_000_000_000);
+const GAS_FOR_FT_TRANSFER: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_RESOLVE_VAULT_WITHDRAW: Gas = Gas(5_000_000_000_000);
+/// Storage the share token state takes on top of its code, paid to the token account.
+const VAULT_TOKEN_STATE_BYTES: u64 = 1_000;
+/// Time a proposal stays open to votes, no other image can be proposed until it ends.
+pub const VAULT_PROPOSAL_DURATION: u64 = 7 * DAY;
+
+#[ext_contract(ext_ft)]
+trait ExternalFt {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
+
+#[ext_contract(ext_self)]
+trait VaultResolver {
+    fn resolve_vault_token(&mut self, root_id: TokenId, owner_id: AccountId, token_deposit: U128);
+    fn resolve_vault_withdraw(&mut self, root_id: TokenId, account_id: AccountId, amount: U128);
+}
+
+/// Image proposed for a vaulted block, shown once more than half of the shares were sent to the
+/// contract to vote for it before `expires_at`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ImageProposal {
+    pub proposal_id: u64,
+    pub proposer_id: AccountId,
+    pub image: BlockImage,
+    /// Shares locked in the contract voting for the proposal.
+    pub votes: U128,
+    pub expires_at: U64,
+}
+
+/// Shares a holder sent to the contract to vote, held until withdrawn.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LockedShares {
+    /// Proposal the shares vote for, `None` once it closed.
+    pub proposal_id: Option<u64>,
+    pub amount: U128,
+}
+
+/// A block held by the contract and owned through fungible shares. The shares are a NEP-141
+/// token of their own, deployed to `v<root_id>-<vault number>.<this contract>` for every vault.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Vault {
+    pub curator_id: AccountId,
+    pub token_account_id: AccountId,
+    pub total_supply: U128,
+    pub proposal: Option<ImageProposal>,
+    pub next_proposal_id: u64,
+    pub locked: LookupMap<AccountId, LockedShares>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VaultView {
+    pub root_id: TokenId,
+    pub curator_id: AccountId,
+    pub token_account_id: AccountId,
+    pub total_supply: U128,
+    pub proposal: Option<ImageProposal>,
+}
+
+/// `msg` of the `ft_transfer_call` sending shares to the contract.
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde", tag = "action", rename_all = "snake_case")]
+enum VaultMsg {
+    /// Propose `image` for the block and vote for it, once no other proposal is open.
+    Propose { root_id: TokenId, image: BlockImage },
+    /// Vote for the open proposal.
+    Vote { root_id: TokenId },
+    /// Burn every share and take the block out of the vault.
+    Redeem { root_id: TokenId },
+}
+
+#[near_bindgen]
+impl FungibleTokenReceiver for Contract {
+    /// Shares sent with `ft_transfer_call` by the share token of the vault named in `msg`. Shares
+    /// sent to propose or vote are locked until withdrawn, shares sent to redeem are burnt. A
+    /// rejected message panics, so the token sends the shares back.
+    fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        let vault_msg: VaultMsg = serde_json::from_str(&msg).unwrap_or_else(|_| env::panic_str("Invalid vault message"));
+        let root_id = match &vault_msg {
+            VaultMsg::Propose { root_id, .. } | VaultMsg::Vote { root_id } | VaultMsg::Redeem { root_id } => root_id.clone(),
+        };
+        let mut vault = self.internal_vault(&root_id);
+        assert_eq!(env::predecessor_account_id(), vault.token_account_id, "Not the share token of the vault");
+
+        match vault_msg {
+            VaultMsg::Propose { image, .. } => {
+                image.assert_valid();
+                let now = env::block_timestamp();
+                if let Some(proposal) = &vault.proposal {
+                    assert!(now >= proposal.expires_at.0, "A proposal is already open");
+                }
+                VaultImagePropose { proposer_id: &sender_id, root_id: &root_id, cid: &image.cid }.emit();
+                vault.proposal = Some(ImageProposal {
+                    proposal_id: vault.next_proposal_id,
+                    proposer_id: sender_id.clone(),
+                    image,
+                    votes: U128(0),
+                    expires_at: U64(now + VAULT_PROPOSAL_DURATION),
+                });
+                vault.next_proposal_id += 1;
+                self.internal_vote_vault_proposal(&root_id, vault, &sender_id, amount.0);
+            }
+            VaultMsg::Vote { .. } => {
+                self.internal_vote_vault_proposal(&root_id, vault, &sender_id, amount.0);
+            }
+            VaultMsg::Redeem { .. } => {
+                assert_eq!(amount, vault.total_supply, "Must send all shares to redeem");
+                self.vaults.remove(&root_id);
+                self.internal_transfer_block(&root_id, &env::current_account_id(), &sender_id, "redeem");
+                VaultRedeem { owner_id: &sender_id, root_id: &root_id }.emit();
+            }
+        }
+        PromiseOrValue::Value(U128(0))
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Owner only, set the code of the NEP-141 share token deployed for every vault.
+    pub fn set_vault_token_code(&mut self, code: Base64VecU8) {
+        assert_eq!(env::predecessor_account_id(), self.tokens.owner_id, "Unauthorized");
+        self.vault_token_code.set(&code.into());
+    }
+
+    /// Move the block rooted at `root_id` into a vault held by the contract and deploy its share
+    /// token with `total_supply` shares minted to the caller. The Harberger account of the block is
+    /// closed and its deposit refunded, vaulted blocks cannot be force-bought. The attached deposit
+    /// must cover the storage of the share token account and of the vault, the excess is refunded.
+    /// The block goes back to the caller if the share token cannot be deployed.
+    #[payable]
+    pub fn fractionalize_block(&mut self, root_id: TokenId, total_supply: U128) {
+        let initial_storage_usage = env::storage_usage();
+        let owner_id = self.internal_block_owner(&root_id);
+        assert_eq!(owner_id, env::predecessor_account_id(), "No permission to fractionalize");
+        assert!(total_supply.0 > 0, "Total supply must be positive");
+        let code = self.vault_token_code.get().unwrap_or_else(|| env::panic_str("Vault token code is not set"));
+        // a redeemed vault leaves its share token behind, so every vault gets a fresh account
+        let token_account_id: AccountId = format!("v{}-{}.{}", root_id, self.next_vault_id, env::current_account_id())
+            .parse()
+            .unwrap_or_else(|_| env::panic_str("Invalid share token account"));
+        self.next_vault_id += 1;
+
+        self.internal_transfer_block(&root_id, &owner_id, &env::current_account_id(), "vault");
+        self.vaults.insert(&root_id, &Vault {
+            curator_id: owner_id.clone(),
+            token_account_id: token_account_id.clone(),
+            total_supply,
+            proposal: None,
+            next_proposal_id: 0,
+            locked: LookupMap::new(StorageKey::VaultLocks { vault_hash: env::sha256(token_account_id.as_bytes()) }),
+        });
+        VaultCreate { owner_id: &owner_id, root_id: &root_id, token_account_id: &token_account_id, total_supply }.emit();
+
+        let token_deposit = Balance::from(code.len() as u64 + VAULT_TOKEN_STATE_BYTES) * env::storage_byte_cost();
+        let required_cost = token_deposit
+            + Balance::from(env::storage_usage().saturating_sub(initial_storage_usage)) * env::storage_byte_cost();
+        let deposit = env::attached_deposit();
+        assert!(deposit >= required_cost, "Must attach {} yoctoNEAR to cover the share token and storage", required_cost);
+        if deposit > required_cost {
+            Promise::new(owner_id.clone()).transfer(deposit - required_cost);
+        }
+
+        let metadata = FungibleTokenMetadata {
+            spec: FT_METADATA_SPEC.to_string(),
+            name: format!("Pixel Land block {}", root_id),
+            symbol: format!("PXL{}", root_id),
+            icon: None,
+            reference: None,
+            reference_hash: None,
+            decimals: 0,
+        };
+        let args = json!({
+            "owner_id": owner_id,
+            "vault_id": env::current_account_id(),
+            "total_supply": total_supply,
+            "metadata": metadata,
+        });
+        Promise::new(token_account_id)
+            .create_account()
+            .transfer(token_deposit)
+            .deploy_contract(code)
+            .function_call("new".to_string(), args.to_string().into_bytes(), 0, GAS_FOR_VAULT_TOKEN_NEW)
+            .then(ext_self::resolve_vault_token(
+                root_id,
+                owner_id,
+                U128(token_deposit),
+                env::current_account_id(),
+                0,
+                GAS_FOR_RESOLVE_VAULT_TOKEN,
+            ));
+    }
+
+    /// Take back the shares the caller sent to vote in the vault of `root_id`. Their votes no
+    /// longer count for the open proposal.
+    #[payable]
+    pub fn withdraw_vault_shares(&mut self, root_id: TokenId) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut vault = self.internal_vault(&root_id);
+        let locked = vault.locked.remove(&account_id).unwrap_or_else(|| env::panic_str("No locked shares"));
+        if let Some(proposal) = vault.proposal.as_mut() {
+            if locked.proposal_id == Some(proposal.proposal_id) {
+                proposal.votes = U128(proposal.votes.0 - locked.amount.0);
+            }
+        }
+        self.vaults.insert(&root_id, &vault);
+
+        ext_ft::ft_transfer(
+            account_id.clone(),
+            locked.amount,
+            Some("vault shares withdrawn".to_string()),
+            vault.token_account_id,
+            1,
+            GAS_FOR_FT_TRANSFER,
+        )
+        .then(ext_self::resolve_vault_withdraw(
+            root_id,
+            account_id,
+            locked.amount,
+            env::current_account_id(),
+            0,
+            GAS_FOR_RESOLVE_VAULT_WITHDRAW,
+        ));
+    }
+
+    /// Close the vault and give the block back if deploying its share token failed.
+    #[private]
+    pub fn resolve_vault_token(&mut self, root_id: TokenId, owner_id: AccountId, token_deposit: U128) {
+        if is_promise_success() {
+            return;
+        }
+        self.vaults.remove(&root_id);
+        self.internal_transfer_block(&root_id, &env::current_account_id(), &owner_id, "vault failed");
+        Promise::new(owner_id.clone()).transfer(token_deposit.0);
+        VaultRedeem { owner_id: &owner_id, root_id: &root_id }.emit();
+    }
+
+    /// Lock the shares again, without a vote, if sending them back failed.
+    #[private]
+    pub fn resolve_vault_withdraw(&mut self, root_id: TokenId, account_id: AccountId, amount: U128) {
+        if is_promise_success() {
+            return;
+        }
+        if let Some(mut vault) = self.vaults.get(&root_id) {
+            let mut locked = vault.locked.get(&account_id).unwrap_or(LockedShares { proposal_id: None, amount: U128(0) });
+            locked.amount = U128(locked.amount.0 + amount.0);
+            vault.locked.insert(&account_id, &locked);
+        }
+    }
+
+    pub fn get_vault(&self, root_id: TokenId) -> Option<VaultView> {
+        let vault = self.vaults.get(&root_id)?;
+        Some(VaultView {
+            root_id,
+            curator_id: vault.curator_id,
+            token_account_id: vault.token_account_id,
+            total_supply: vault.total_supply,
+            proposal: vault.proposal,
+        })
+    }
+
+    pub fn get_vault_locked_shares(&self, root_id: TokenId, account_id: AccountId) -> Option<LockedShares> {
+        self.vaults.get(&root_id)?.locked.get(&account_id)
+    }
+}
+
+impl Contract {
+    fn internal_vault(&self, root_id: &TokenId) -> Vault {
+        self.vaults.get(root_id).unwrap_or_else(|| env::panic_str("Block is not in a vault"))
+    }
+
+    /// Lock `amount` shares of `voter_id` and count them, with the shares they locked before, for
+    /// the open proposal. The proposed image is shown once more than half of the shares voted for
+    /// it, then the vault is stored.
+    fn internal_vote_vault_proposal(&mut self, root_id: &TokenId, mut vault: Vault, voter_id: &AccountId, amount: Balance) {
+        let proposal = vault.proposal.as_mut().unwrap_or_else(|| env::panic_str("No open proposal"));
+        assert!(env::block_timestamp() < proposal.expires_at.0, "Proposal expired");
+        let mut locked = vault.locked.get(voter_id).unwrap_or(LockedShares { proposal_id: None, amount: U128(0) });
+        let votes = if locked.proposal_id == Some(proposal.proposal_id) { amount } else { locked.amount.0 + amount };
+        locked.proposal_id = Some(proposal.proposal_id);
+        locked.amount = U128(locked.amount.0 + amount);
+        vault.locked.insert(voter_id, &locked);
+        proposal.votes = U128(proposal.votes.0 + votes);
+        VaultVote { voter_id, root_id, amount: U128(votes) }.emit();
+
+        if proposal.votes.0 * 2 > vault.total_supply.0 {
+            let proposal = vault.proposal.take().unwrap();
+            self.internal_set_block_image(root_id, proposal.image, &proposal.proposer_id);
+        }
+        self.vaults.insert(root_id, &vault);
+    }
+}
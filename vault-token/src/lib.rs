@@ -0,0 +1,117 @@
+/*!
+NEP-141 share token of a Pixel Land vault.
+
+The Pixel Land contract deploys one of these to `v<root_id>-<n>.<pixel land account>` for every
+fractionalized block. The whole supply is minted to the account that fractionalized the block,
+the Pixel Land contract is registered so shares can be sent to it with `ft_transfer_call` to
+vote on the block image or redeem the block.
+*/
+use near_contract_standards::fungible_token::events::FtMint;
+use near_contract_standards::fungible_token::metadata::{FungibleTokenMetadata, FungibleTokenMetadataProvider};
+use near_contract_standards::fungible_token::FungibleToken;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::LazyOption;
+use near_sdk::json_types::U128;
+use near_sdk::{env, log, near_bindgen, AccountId, Balance, BorshStorageKey, PanicOnDefault, PromiseOrValue};
+
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+pub struct Contract {
+    token: FungibleToken,
+    metadata: LazyOption<FungibleTokenMetadata>,
+    /// Pixel Land contract holding the block.
+    vault_id: AccountId,
+}
+
+#[derive(BorshSerialize, BorshStorageKey)]
+enum StorageKey {
+    FungibleToken,
+    Metadata,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Mint `total_supply` shares to `owner_id` and register `vault_id`, the Pixel Land contract.
+    #[init]
+    pub fn new(owner_id: AccountId, vault_id: AccountId, total_supply: U128, metadata: FungibleTokenMetadata) -> Self {
+        assert!(!env::state_exists(), "Already initialized");
+        metadata.assert_valid();
+        let mut this = Self {
+            token: FungibleToken::new(StorageKey::FungibleToken),
+            metadata: LazyOption::new(StorageKey::Metadata, Some(&metadata)),
+            vault_id,
+        };
+        this.token.internal_register_account(&owner_id);
+        if this.vault_id != owner_id {
+            this.token.internal_register_account(&this.vault_id);
+        }
+        this.token.internal_deposit(&owner_id, total_supply.into());
+        FtMint { owner_id: &owner_id, amount: &total_supply, memo: Some("vault shares") }.emit();
+        this
+    }
+
+    pub fn get_vault_id(&self) -> AccountId {
+        self.vault_id.clone()
+    }
+
+    fn on_account_closed(&mut self, account_id: AccountId, balance: Balance) {
+        log!("Closed @{} with {}", account_id, balance);
+    }
+
+    fn on_tokens_burned(&mut self, account_id: AccountId, amount: Balance) {
+        log!("Account @{} burned {}", account_id, amount);
+    }
+}
+
+near_contract_standards::impl_fungible_token_core!(Contract, token, on_tokens_burned);
+near_contract_standards::impl_fungible_token_storage!(Contract, token, on_account_closed);
+
+#[near_bindgen]
+impl FungibleTokenMetadataProvider for Contract {
+    fn ft_metadata(&self) -> FungibleTokenMetadata {
+        self.metadata.get().unwrap()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_contract_standards::fungible_token::metadata::FT_METADATA_SPEC;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    fn metadata() -> FungibleTokenMetadata {
+        FungibleTokenMetadata {
+            spec: FT_METADATA_SPEC.to_string(),
+            name: "Pixel Land block 7".to_string(),
+            symbol: "PXL7".to_string(),
+            icon: None,
+            reference: None,
+            reference_hash: None,
+            decimals: 0,
+        }
+    }
+
+    #[test]
+    fn test_new() {
+        testing_env!(VMContextBuilder::new().current_account_id(accounts(3)).build());
+        let contract = Contract::new(accounts(1), accounts(0), U128(100), metadata());
+        assert_eq!(contract.ft_total_supply(), U128(100));
+        assert_eq!(contract.ft_balance_of(accounts(1)), U128(100));
+        assert_eq!(contract.ft_balance_of(accounts(0)), U128(0));
+        assert_eq!(contract.ft_metadata().symbol, "PXL7");
+    }
+
+    #[test]
+    fn test_transfer_to_vault() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.current_account_id(accounts(3)).build());
+        let mut contract = Contract::new(accounts(1), accounts(0), U128(100), metadata());
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.ft_transfer(accounts(0), U128(40), None);
+        assert_eq!(contract.ft_balance_of(accounts(0)), U128(40));
+        assert_eq!(contract.ft_balance_of(accounts(1)), U128(60));
+    }
+}